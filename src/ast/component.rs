@@ -0,0 +1,122 @@
+use std::fmt::Display;
+
+use serde_json::Value as JsonValue;
+
+use crate::{ast::nodes::Node, spans::Spanned};
+
+/// The type a component property is declared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropType {
+    Any,
+    String,
+    Int,
+    /// Any number, integers included.
+    Float,
+    Bool,
+}
+impl PropType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let ty = match name {
+            "any" => Self::Any,
+            "string" => Self::String,
+            "int" => Self::Int,
+            "float" | "number" => Self::Float,
+            "bool" => Self::Bool,
+            _ => return None,
+        };
+        Some(ty)
+    }
+    /// Whether `value` is of this type, null is accepted by every type.
+    pub fn accepts(&self, value: &JsonValue) -> bool {
+        match (self, value) {
+            (Self::Any, _) | (_, JsonValue::Null) => true,
+            (Self::String, JsonValue::String(_)) => true,
+            (Self::Int, JsonValue::Number(num)) => num.is_i64() || num.is_u64(),
+            (Self::Float, JsonValue::Number(_)) => true,
+            (Self::Bool, JsonValue::Bool(_)) => true,
+            _ => false,
+        }
+    }
+}
+impl Display for PropType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Any => "any",
+            Self::String => "string",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+        };
+        write!(f, "{name}")
+    }
+}
+/// A property a component accepts, declared like `title: string` or `subtitle?: string`.
+#[derive(Debug, Clone)]
+pub struct PropDecl {
+    pub name: String,
+    pub ty: PropType,
+    pub required: bool,
+}
+impl PropDecl {
+    pub fn required(name: impl Into<String>, ty: PropType) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            required: true,
+        }
+    }
+    pub fn optional(name: impl Into<String>, ty: PropType) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            required: false,
+        }
+    }
+    /// Parses a comma separated list of declarations like `title: string, body?`.
+    ///
+    /// A `?` after the name makes the property optional, and properties without a type are `any`.
+    pub fn parse_list(decls: &str) -> Result<Vec<Self>, String> {
+        let mut parsed = vec![];
+        for decl in decls
+            .split(',')
+            .map(str::trim)
+            .filter(|decl| !decl.is_empty())
+        {
+            let (name, ty) = match decl.split_once(':') {
+                Some((name, ty)) => {
+                    let ty = ty.trim();
+                    let Some(ty) = PropType::from_name(ty) else {
+                        return Err(format!("Unknown type '{ty}'"));
+                    };
+                    (name.trim(), ty)
+                }
+                None => (decl, PropType::Any),
+            };
+            let (name, required) = match name.strip_suffix('?') {
+                Some(name) => (name.trim_end(), false),
+                None => (name, true),
+            };
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!("Invalid property name '{name}'"));
+            }
+            parsed.push(Self {
+                name: name.to_owned(),
+                ty,
+                required,
+            });
+        }
+        Ok(parsed)
+    }
+}
+/// A reusable component, its body is rendered in place of every element named after it.
+#[derive(Debug, Clone)]
+pub struct ComponentDef {
+    pub name: String,
+    pub props: Vec<PropDecl>,
+    pub body: Vec<Spanned<Node>>,
+}
+impl ComponentDef {
+    pub fn get_prop(&self, name: &str) -> Option<&PropDecl> {
+        self.props.iter().find(|prop| prop.name == name)
+    }
+}
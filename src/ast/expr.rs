@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use crate::{ast::nodes::Value, spans::Spanned};
+
+/// Filter opting a value out of escaping, it is parsed into [`Expr::Safe`].
+pub const SAFE_FILTER: &str = "safe";
+/// Filter escaping a value, when it is applied last the value is escaped for where it is written.
+pub const ESCAPE_FILTER: &str = "escape";
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Literal(Value),
+    /// A name looked up in the render context like `user`.
+    Variable(String),
+    /// Field access like `user.name`.
+    Member {
+        object: Box<Spanned<Expr>>,
+        field: Spanned<String>,
+    },
+    /// Indexing like `items[0]` or `user["name"]`.
+    Index {
+        object: Box<Spanned<Expr>>,
+        index: Box<Spanned<Expr>>,
+    },
+    Unary {
+        op: Spanned<UnaryOp>,
+        expr: Box<Spanned<Expr>>,
+    },
+    Binary {
+        op: Spanned<BinaryOp>,
+        lhs: Box<Spanned<Expr>>,
+        rhs: Box<Spanned<Expr>>,
+    },
+    /// `cond ? then : otherwise`
+    Ternary {
+        cond: Box<Spanned<Expr>>,
+        then: Box<Spanned<Expr>>,
+        otherwise: Box<Spanned<Expr>>,
+    },
+    /// A value written without escaping, made with the `safe` filter like `{html | safe}`.
+    Safe(Box<Spanned<Expr>>),
+    /// A filter applied through a pipe like `name | truncate(20)`.
+    Filter {
+        value: Box<Spanned<Expr>>,
+        name: Spanned<String>,
+        args: Vec<Spanned<Expr>>,
+    },
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    NotEq,
+    Lesser,
+    LesserEq,
+    Greater,
+    GreaterEq,
+    And,
+    Or,
+    /// `a ?? b`, evaluates to `b` when `a` is null or undefined.
+    Coalesce,
+}
+impl BinaryOp {
+    /// The left and right binding power of the operator.
+    ///
+    /// Higher binds tighter, a right power lower than the left one makes it right associative.
+    pub fn binding_power(&self) -> (u8, u8) {
+        use BinaryOp as B;
+        match self {
+            B::Coalesce => (3, 4),
+            B::Or => (5, 6),
+            B::And => (7, 8),
+            B::Eq | B::NotEq => (9, 10),
+            B::Lesser | B::LesserEq | B::Greater | B::GreaterEq => (11, 12),
+            B::Add | B::Sub => (13, 14),
+            B::Mul | B::Div | B::Rem => (15, 16),
+        }
+    }
+}
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Neg => "-",
+        };
+        write!(f, "{op}")
+    }
+}
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use BinaryOp as B;
+        let op = match self {
+            B::Add => "+",
+            B::Sub => "-",
+            B::Mul => "*",
+            B::Div => "/",
+            B::Rem => "%",
+            B::Eq => "==",
+            B::NotEq => "!=",
+            B::Lesser => "<",
+            B::LesserEq => "<=",
+            B::Greater => ">",
+            B::GreaterEq => ">=",
+            B::And => "&&",
+            B::Or => "||",
+            B::Coalesce => "??",
+        };
+        write!(f, "{op}")
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    lang_errors::{LangMessage, MsgBuilder},
+    spans::*,
+};
+
+use super::{Lint, LintLevel};
+
+#[derive(Debug, Clone)]
+pub enum LintWarning {
+    /// An attribute whose name only differs in case from the one of an earlier attribute.
+    DuplicateAttribute {
+        element: String,
+        attribute: String,
+        first_name: String,
+        first: Span,
+    },
+    EmptyText,
+    DeprecatedElement(String),
+    /// An end tag of the other kind than the first end tag of the file, which is at `first`.
+    MixedEndTags {
+        generic: bool,
+        first: Span,
+    },
+}
+impl LintWarning {
+    /// The lint that finds this.
+    pub fn lint(&self) -> Lint {
+        match self {
+            Self::DuplicateAttribute { .. } => Lint::DuplicateAttributes,
+            Self::EmptyText => Lint::EmptyText,
+            Self::DeprecatedElement(_) => Lint::DeprecatedElements,
+            Self::MixedEndTags { .. } => Lint::MixedEndTags,
+        }
+    }
+}
+/// A [`LintWarning`] along with the level its lint is set to, denied ones are errors.
+#[derive(Debug, Clone)]
+pub struct LintReport {
+    pub level: LintLevel,
+    pub warning: LintWarning,
+}
+impl LangMessage for Spanned<LintReport> {
+    fn diagnostic(&self) -> Diagnostic {
+        use LintWarning as Lw;
+        let lint = self.warning.lint();
+        let severity = match self.level {
+            LintLevel::Deny => Severity::Error,
+            _ => lint.severity(),
+        };
+        let msg = match &self.warning {
+            Lw::DuplicateAttribute {
+                element,
+                attribute,
+                first_name,
+                first,
+            } => MsgBuilder::build(
+                severity,
+                format!("'{element}' has the attribute '{attribute}' twice"),
+                self.span,
+            )
+            .with_code("T0601")
+            .with_err_label(format!("This is the same attribute as '{first_name}'."))
+            .with_label(*first, "It is first set here.")
+            .with_note("Html attribute names are not case sensitive, only the first one is used."),
+            Lw::EmptyText => MsgBuilder::build(severity, "Text that renders nothing", self.span)
+                .with_code("T0602")
+                .with_err_label("This is always empty.")
+                .with_help("Remove it."),
+            Lw::DeprecatedElement(name) => {
+                MsgBuilder::build(severity, format!("'{name}' is deprecated"), self.span)
+                    .with_code("T0603")
+                    .with_err_label("The schema marks this element as deprecated.")
+            }
+            Lw::MixedEndTags { generic, first } => {
+                let (this, other) = match generic {
+                    true => ("'</>'", "named end tags"),
+                    false => ("a named end tag", "'</>'"),
+                };
+                MsgBuilder::build(
+                    severity,
+                    format!("Element closed with {this} in a template using {other}"),
+                    self.span,
+                )
+                .with_code("T0604")
+                .with_err_label("This end tag is of the other kind.")
+                .with_label(*first, "The first end tag of the template is here.")
+                .with_help("Close elements the same way in the whole template.")
+            }
+        };
+        msg.with_note(format!(
+            "'{lint}' is set to {}, its level is set in the \"$lints\" of the schema.",
+            self.level
+        ))
+        .finish()
+    }
+}
@@ -0,0 +1,241 @@
+use std::{collections::HashMap, fmt::Display};
+
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    ast::{expr::Expr, nodes::*, schema::ElementSchema},
+    diagnostic::Severity,
+    spans::{FileID, IntoSpanned, Span, Spanned},
+};
+
+mod error;
+pub use error::*;
+
+/// The lints that run over templates, set to a [`LintLevel`] in the `"$lints"` of a schema file by their name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lint {
+    /// Attributes of an element whose names only differ in case, html only keeps the first one.
+    DuplicateAttributes,
+    /// Text that renders nothing, like an interpolation of `""`.
+    EmptyText,
+    /// Elements the schema marks as `deprecated`.
+    DeprecatedElements,
+    /// A template closing elements with both the generic `</>` and named end tags.
+    MixedEndTags,
+}
+impl Lint {
+    pub const ALL: &[Lint] = &[
+        Self::DuplicateAttributes,
+        Self::EmptyText,
+        Self::DeprecatedElements,
+        Self::MixedEndTags,
+    ];
+    /// The name the lint is configured with.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DuplicateAttributes => "duplicate-attributes",
+            Self::EmptyText => "empty-text",
+            Self::DeprecatedElements => "deprecated-elements",
+            Self::MixedEndTags => "mixed-end-tags",
+        }
+    }
+    /// The severity of what the lint finds when it is set to warn, the ones only
+    /// about style are advice.
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::DuplicateAttributes | Self::DeprecatedElements => Severity::Warning,
+            Self::EmptyText | Self::MixedEndTags => Severity::Advice,
+        }
+    }
+}
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The lint does not run.
+    Allow,
+    /// What the lint finds is reported as a warning or advice.
+    #[default]
+    Warn,
+    /// What the lint finds is reported as an error, parsing fails.
+    Deny,
+}
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+/// The levels lints are set to, every lint is at [`LintLevel::Warn`] unless set otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(transform = add_lint_names)]
+pub struct LintLevels(pub HashMap<Lint, LintLevel>);
+/// Limits the keys of [`LintLevels`] to the lint names, schemars only describes the values of maps.
+fn add_lint_names(schema: &mut Schema) {
+    let names = Lint::ALL.iter().map(|lint| lint.name()).collect::<Vec<_>>();
+    schema.insert("propertyNames".to_owned(), json!({ "enum": names }));
+}
+impl LintLevels {
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.0.get(&lint).copied().unwrap_or_default()
+    }
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.0.insert(lint, level);
+    }
+    /// Sets the levels of `overrides` on top of these ones.
+    pub fn merge(&mut self, overrides: impl IntoIterator<Item = (Lint, LintLevel)>) {
+        self.0.extend(overrides);
+    }
+}
+/// Runs the lints that are not allowed in `schema` over `nodes`.
+///
+/// Returns what they found in the order of the source, each thing only once even when
+/// it is in the body of a component used several times.
+pub fn lint(nodes: &[Spanned<Node>], schema: &ElementSchema) -> Vec<Spanned<LintReport>> {
+    let mut linter = Linter {
+        schema,
+        found: vec![],
+        end_tags: vec![],
+    };
+    linter.lint_nodes(nodes);
+    linter.check_end_tags();
+    let mut found: Vec<_> = linter
+        .found
+        .into_iter()
+        .filter_map(|warning| {
+            let level = schema.lints.level(warning.lint());
+            let report = LintReport {
+                level,
+                warning: warning.item,
+            };
+            (level != LintLevel::Allow).then(|| report.to_spanned(warning.span))
+        })
+        .collect();
+    found.sort_by_key(|report| (report.span.file_id, report.span.start, report.span.end));
+    found.dedup_by(|a, b| a.span == b.span && a.warning.lint() == b.warning.lint());
+    found
+}
+struct Linter<'a> {
+    schema: &'a ElementSchema,
+    found: Vec<Spanned<LintWarning>>,
+    /// Every end tag seen, with the span of the end tag.
+    end_tags: Vec<(EndTag, Span)>,
+}
+impl Linter<'_> {
+    fn lint_nodes(&mut self, nodes: &[Spanned<Node>]) {
+        for node in nodes {
+            match &node.item {
+                Node::Element(element) => self.lint_element(element),
+                Node::Text(text) if text.is_empty() => {
+                    self.found
+                        .push(LintWarning::EmptyText.to_spanned(node.span));
+                }
+                Node::Interpolation(expr) if renders_nothing(&expr.item) => {
+                    self.found
+                        .push(LintWarning::EmptyText.to_spanned(node.span));
+                }
+                _ => {}
+            }
+            for children in node.item.children() {
+                self.lint_nodes(children);
+            }
+        }
+    }
+    fn lint_element(&mut self, element: &Element) {
+        if self
+            .schema
+            .get_rule(&element.name)
+            .is_some_and(|rules| rules.deprecated)
+        {
+            let warning = LintWarning::DeprecatedElement(element.name.clone());
+            self.found.push(warning.to_spanned(element.start_tag_span));
+        }
+        if let Some(span) = element.end_tag_span
+            && element.end_tag != EndTag::None
+        {
+            self.end_tags.push((element.end_tag, span));
+        }
+        let mut by_name: HashMap<String, Vec<(&String, Span)>> = HashMap::new();
+        for (name, Prop { name_span, .. }) in &element.props {
+            by_name
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .push((name, *name_span));
+        }
+        for props in by_name.into_values().filter(|props| props.len() > 1) {
+            let (first_name, first) = props[0];
+            for (name, span) in &props[1..] {
+                let warning = LintWarning::DuplicateAttribute {
+                    element: element.name.clone(),
+                    attribute: (*name).clone(),
+                    first_name: first_name.clone(),
+                    first,
+                };
+                self.found.push(warning.to_spanned(*span));
+            }
+        }
+    }
+    /// Reports the end tags of each file that are not of the kind the file uses first.
+    fn check_end_tags(&mut self) {
+        let mut by_file: HashMap<FileID, Vec<(EndTag, Span)>> = HashMap::new();
+        for (kind, span) in &self.end_tags {
+            by_file
+                .entry(span.file_id)
+                .or_default()
+                .push((*kind, *span));
+        }
+        for mut end_tags in by_file.into_values() {
+            end_tags.sort_by_key(|(_, span)| span.start);
+            let (first_kind, first) = end_tags[0];
+            for (kind, span) in end_tags {
+                if kind != first_kind {
+                    let warning = LintWarning::MixedEndTags {
+                        generic: kind == EndTag::Generic,
+                        first,
+                    };
+                    self.found.push(warning.to_spanned(span));
+                }
+            }
+        }
+    }
+}
+/// Whether the interpolated `expr` always renders as nothing.
+fn renders_nothing(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Value::String(text)) => text.is_empty(),
+        Expr::Literal(Value::Null) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, ast::schema::ElementSchema, filestore::FileStore};
+
+    use super::*;
+
+    #[test]
+    fn reports_duplicate_attributes_at_their_name() {
+        let template = r#"<a href="x" HREF="y"></a>"#;
+        let nodes = Compiler::make(FileStore::new(), true)
+            .parse(template)
+            .unwrap();
+        let reports = lint(&nodes, &ElementSchema::html5());
+        assert_eq!(reports.len(), 1);
+        let LintWarning::DuplicateAttribute { first, .. } = &reports[0].warning else {
+            panic!("Expected a duplicate attribute");
+        };
+        assert_eq!((first.start, first.end), (3, 7));
+        assert_eq!((reports[0].span.start, reports[0].span.end), (12, 16));
+    }
+}
@@ -1,3 +1,7 @@
+pub mod component;
+pub mod expr;
+pub mod lint;
 pub mod nodes;
 pub mod parser;
 pub mod schema;
+pub mod validate;
@@ -1,19 +1,147 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
-use crate::spans::{Span, Spanned};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::{component::PropType, expr::Expr},
+    spans::{Span, Spanned},
+};
 #[derive(Debug, Clone)]
 pub struct Element {
     pub name: String,
-    pub props: HashMap<String, Spanned<Value>>,
+    pub props: IndexMap<String, Prop>,
     pub children: Vec<Spanned<Node>>,
     pub start_tag_span: Span,
     pub end_tag_span: Option<Span>,
+    pub end_tag: EndTag,
+}
+/// A property of an element like `href="/home"`.
+#[derive(Debug, Clone)]
+pub struct Prop {
+    /// Where the name of the property is written.
+    pub name_span: Span,
+    /// The value, a bare property like `hidden` is `true` spanning its name.
+    pub value: Spanned<Value>,
+}
+/// How an element is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EndTag {
+    /// The element closes itself like `<br/>`, is void or its end tag was left out.
+    #[default]
+    None,
+    /// An end tag naming the element like `</div>`.
+    Named,
+    /// The generic end tag `</>`.
+    Generic,
 }
 #[derive(Debug, Clone)]
 pub enum Node {
     Text(String),
     Comment(String),
     Element(Element),
+    /// An expression in braces like `{user.name}` that is written as text.
+    Interpolation(Spanned<Expr>),
+    /// The verbatim content of an element whose schema rules parse it raw, like a `<script>`.
+    Raw(String),
+    /// `<if>` followed by any `<else-if>` and an optional `<else>`.
+    If {
+        branches: Vec<Spanned<IfBranch>>,
+        else_branch: Option<Spanned<Vec<Spanned<Node>>>>,
+    },
+    For(ForLoop),
+    /// A use of a template component, expanded into the component's body.
+    Component(ComponentCall),
+    /// The children given to the innermost component, rendered where it has `<children/>`.
+    Slot(Vec<Spanned<Node>>),
+    /// Placeholder for content that could not be parsed, only left by [`Parser::parse_recovering`].
+    ///
+    /// [`Parser::parse_recovering`]: crate::ast::parser::Parser::parse_recovering
+    Error,
+}
+impl Node {
+    /// Every list of child nodes this node holds, like the branches of an `<if>`.
+    pub fn children(&self) -> Vec<&Vec<Spanned<Node>>> {
+        match self {
+            Node::Element(element) => vec![&element.children],
+            Node::If {
+                branches,
+                else_branch,
+            } => branches
+                .iter()
+                .map(|branch| &branch.item.children)
+                .chain(else_branch.iter().map(|children| &children.item))
+                .collect(),
+            Node::For(for_loop) => std::iter::once(&for_loop.children)
+                .chain(for_loop.empty.iter().map(|children| &children.item))
+                .collect(),
+            Node::Component(call) => vec![&call.body],
+            Node::Slot(children) => vec![children],
+            Node::Text(_)
+            | Node::Comment(_)
+            | Node::Interpolation(_)
+            | Node::Raw(_)
+            | Node::Error => vec![],
+        }
+    }
+    /// Mutable version of [`Node::children`].
+    pub fn children_mut(&mut self) -> Vec<&mut Vec<Spanned<Node>>> {
+        match self {
+            Node::Element(element) => vec![&mut element.children],
+            Node::If {
+                branches,
+                else_branch,
+            } => branches
+                .iter_mut()
+                .map(|branch| &mut branch.item.children)
+                .chain(else_branch.iter_mut().map(|children| &mut children.item))
+                .collect(),
+            Node::For(for_loop) => std::iter::once(&mut for_loop.children)
+                .chain(for_loop.empty.iter_mut().map(|children| &mut children.item))
+                .collect(),
+            Node::Component(call) => vec![&mut call.body],
+            Node::Slot(children) => vec![children],
+            Node::Text(_)
+            | Node::Comment(_)
+            | Node::Interpolation(_)
+            | Node::Raw(_)
+            | Node::Error => vec![],
+        }
+    }
+}
+/// A `<for each={items} as="item" index="i">` element.
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    /// The array or object iterated over.
+    pub each: Spanned<Expr>,
+    /// The name each item is bound to.
+    pub binding: Spanned<String>,
+    /// The name the array index or object key is bound to.
+    pub index: Option<Spanned<String>>,
+    pub children: Vec<Spanned<Node>>,
+    /// Rendered instead of the children when there is nothing to iterate.
+    pub empty: Option<Spanned<Vec<Spanned<Node>>>>,
+}
+/// An element like `<Card title="x">` using a component.
+#[derive(Debug, Clone)]
+pub struct ComponentCall {
+    pub name: String,
+    pub props: Vec<BoundProp>,
+    /// The component's body, with its slots filled in with the children of the call.
+    pub body: Vec<Spanned<Node>>,
+    pub start_tag_span: Span,
+}
+/// A property given to a component, evaluated where the component is used.
+#[derive(Debug, Clone)]
+pub struct BoundProp {
+    pub name: String,
+    pub ty: PropType,
+    pub value: Spanned<Expr>,
+}
+/// A `<if cond={...}>` or `<else-if cond={...}>` element.
+#[derive(Debug, Clone)]
+pub struct IfBranch {
+    pub cond: Spanned<Expr>,
+    pub children: Vec<Spanned<Node>>,
 }
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -23,6 +151,46 @@ pub enum Value {
     Null,
     Bool(bool),
     Element,
+    /// An expression in braces like `href={user.url}`, evaluated at render time.
+    Expr(Box<Spanned<Expr>>),
+}
+impl Value {
+    /// Converts a literal into the value it has at render time.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            Value::Int(num) => JsonValue::from(*num),
+            Value::Float(num) => JsonValue::from(*num),
+            Value::String(text) => JsonValue::String(text.clone()),
+            Value::Bool(cond) => JsonValue::Bool(*cond),
+            Value::Null | Value::Element | Value::Expr(_) => JsonValue::Null,
+        }
+    }
+    /// Converts a json value into a literal, arrays and objects have no literal form.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let value = match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::Bool(cond) => Value::Bool(*cond),
+            JsonValue::Number(num) => match num.as_i64() {
+                Some(num) => Value::Int(num),
+                None => Value::Float(num.as_f64()?),
+            },
+            JsonValue::String(text) => Value::String(text.clone()),
+            JsonValue::Array(_) | JsonValue::Object(_) => return None,
+        };
+        Some(value)
+    }
+    /// The name of the value's type as shown in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Element => "element",
+            Value::Expr(_) => "expression",
+        }
+    }
 }
 pub trait IntoNodespan {
     fn to_nodespan(self, span: Span) -> Spanned<Node>;
@@ -48,30 +216,33 @@ macro_rules! nodes_from {
 nodes_from!(Element);
 pub struct ElementBuilder {
     name: String,
-    props: HashMap<String, Spanned<Value>>,
+    props: IndexMap<String, Prop>,
     children: Vec<Spanned<Node>>,
     start_tag_span: Span,
     end_tag_span: Option<Span>,
+    end_tag: EndTag,
 }
 impl ElementBuilder {
     pub fn new(name: impl AsRef<str>, start_tag_span: Span) -> Self {
         ElementBuilder {
             name: name.as_ref().to_owned(),
-            props: HashMap::new(),
+            props: IndexMap::new(),
             children: vec![],
             start_tag_span,
             end_tag_span: None,
+            end_tag: EndTag::None,
         }
     }
-    pub fn with_props(self, props: HashMap<String, Spanned<Value>>) -> Self {
+    pub fn with_props(self, props: IndexMap<String, Prop>) -> Self {
         Self { props, ..self }
     }
     pub fn with_children(self, children: Vec<Spanned<Node>>) -> Self {
         Self { children, ..self }
     }
 
-    pub fn with_end_tag_span(self, end_tag_span: Span) -> Self {
+    pub fn with_end_tag(self, end_tag: EndTag, end_tag_span: Span) -> Self {
         Self {
+            end_tag,
             end_tag_span: Some(end_tag_span),
             ..self
         }
@@ -84,6 +255,7 @@ impl ElementBuilder {
             children: self.children,
             start_tag_span: self.start_tag_span,
             end_tag_span: self.end_tag_span,
+            end_tag: self.end_tag,
         }
     }
     pub fn finish_node(self, span: Span) -> Spanned<Node> {
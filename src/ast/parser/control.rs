@@ -0,0 +1,177 @@
+use crate::{
+    ast::{expr::Expr, nodes::*},
+    spans::{IntoSpanned, Span, Spanned},
+};
+
+use super::{ParseError, Parser, Result, err};
+
+pub const IF_TAG: &str = "if";
+pub const ELSE_IF_TAG: &str = "else-if";
+pub const ELSE_TAG: &str = "else";
+pub const FOR_TAG: &str = "for";
+pub const EMPTY_TAG: &str = "empty";
+
+/// Converts a property into an expression, literals become [`Expr::Literal`].
+pub(super) fn prop_to_expr(value: Spanned<Value>) -> Spanned<Expr> {
+    match value.item {
+        Value::Expr(expr) => *expr,
+        literal => Expr::Literal(literal).to_spanned(value.span),
+    }
+}
+/// Takes the required property `prop` out of `element`.
+pub(super) fn take_prop(element: &mut Element, prop: &str) -> Result<Spanned<Value>> {
+    let Some(Prop { value, .. }) = element.props.shift_remove(prop) else {
+        return err(ParseError::MissingProp {
+            element: element.name.clone(),
+            prop: prop.to_owned(),
+        }
+        .to_spanned(element.start_tag_span));
+    };
+    Ok(value)
+}
+/// Takes the required property `prop` out of `element`, it has to be a string that
+/// is used as a variable name.
+fn take_name_prop(element: &mut Element, prop: &str) -> Result<Spanned<String>> {
+    let value = take_prop(element, prop)?;
+    match value.item {
+        Value::String(name) if is_identifier(&name) => Ok(name.to_spanned(value.span)),
+        _ => err(ParseError::InvalidName(prop.to_owned()).to_spanned(value.span)),
+    }
+}
+/// Whether `name` can be used as a variable in expressions.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == ':')
+}
+/// Whether `node` can sit between the branches of a conditional.
+fn is_filler(node: &Node) -> bool {
+    match node {
+        Node::Text(text) => text.trim().is_empty(),
+        Node::Comment(_) => true,
+        _ => false,
+    }
+}
+fn element_named<'a>(node: &'a Spanned<Node>, names: &[&str]) -> Option<&'a Element> {
+    match &node.item {
+        Node::Element(element) if names.contains(&element.name.as_str()) => Some(element),
+        _ => None,
+    }
+}
+impl<'input> Parser<'input> {
+    /// Turns the built in control flow elements among the children of `parent` into their nodes.
+    ///
+    /// `<if>`, `<else-if>` and `<else>` siblings become a [`Node::If`] and `<for>` a [`Node::For`].
+    pub(super) fn fold_control_flow(
+        &mut self,
+        children: Vec<Spanned<Node>>,
+        parent: Option<&str>,
+    ) -> Result<Vec<Spanned<Node>>> {
+        let mut grouped: Vec<Spanned<Node>> = Vec::with_capacity(children.len());
+        let mut iter = children.into_iter().peekable();
+        while let Some(node) = iter.next() {
+            if let Some(element) = element_named(&node, &[ELSE_IF_TAG, ELSE_TAG]) {
+                return err(ParseError::MisplacedElse(element.name.clone())
+                    .to_spanned(element.start_tag_span));
+            }
+            if let Some(element) = element_named(&node, &[EMPTY_TAG])
+                && parent != Some(FOR_TAG)
+            {
+                return err(ParseError::MisplacedEmpty.to_spanned(element.start_tag_span));
+            }
+            let Node::Element(element) = node.item else {
+                grouped.push(node);
+                continue;
+            };
+            if element.name == FOR_TAG {
+                grouped.push(self.make_for(element, node.span)?);
+                continue;
+            }
+            if element.name != IF_TAG {
+                grouped.push(Node::Element(element).to_spanned(node.span));
+                continue;
+            }
+            let mut span = node.span;
+            let mut branches = vec![self.make_branch(element, node.span)?];
+            let mut else_branch = None;
+            let mut fillers = vec![];
+            while let Some(next) = iter.next_if(|next| {
+                is_filler(next) || element_named(next, &[ELSE_IF_TAG, ELSE_TAG]).is_some()
+            }) {
+                if is_filler(&next) {
+                    fillers.push(next);
+                    continue;
+                }
+                let Node::Element(next_element) = next.item else {
+                    unreachable!("Only elements are matched")
+                };
+                if else_branch.is_some() {
+                    return err(ParseError::MisplacedElse(next_element.name)
+                        .to_spanned(next_element.start_tag_span));
+                }
+                // Whitespace and comments between branches are not rendered
+                fillers.clear();
+                span = span + next.span;
+                if next_element.name == ELSE_IF_TAG {
+                    branches.push(self.make_branch(next_element, next.span)?);
+                } else {
+                    else_branch = Some(next_element.children.to_spanned(next.span));
+                }
+            }
+            grouped.push(
+                Node::If {
+                    branches,
+                    else_branch,
+                }
+                .to_spanned(span),
+            );
+            grouped.append(&mut fillers);
+        }
+        Ok(grouped)
+    }
+    fn make_for(&mut self, mut element: Element, span: Span) -> Result<Spanned<Node>> {
+        let each = prop_to_expr(take_prop(&mut element, "each")?);
+        let binding = take_name_prop(&mut element, "as")?;
+        let index = if element.props.contains_key("index") {
+            Some(take_name_prop(&mut element, "index")?)
+        } else {
+            None
+        };
+        let mut children = Vec::with_capacity(element.children.len());
+        let mut empty: Option<Spanned<Vec<Spanned<Node>>>> = None;
+        for child in element.children {
+            let Node::Element(child_element) = child.item else {
+                children.push(child);
+                continue;
+            };
+            if child_element.name != EMPTY_TAG {
+                children.push(Node::Element(child_element).to_spanned(child.span));
+                continue;
+            }
+            if let Some(first) = &empty {
+                return err(ParseError::DuplicateEmpty { first: first.span }
+                    .to_spanned(child_element.start_tag_span));
+            }
+            empty = Some(child_element.children.to_spanned(child.span));
+        }
+        let for_loop = ForLoop {
+            each,
+            binding,
+            index,
+            children,
+            empty,
+        };
+        Ok(Node::For(for_loop).to_spanned(span))
+    }
+    fn make_branch(&mut self, mut element: Element, span: Span) -> Result<Spanned<IfBranch>> {
+        let cond = prop_to_expr(take_prop(&mut element, "cond")?);
+        let branch = IfBranch {
+            cond,
+            children: element.children,
+        };
+        Ok(branch.to_spanned(span))
+    }
+}
@@ -1,5 +1,4 @@
-use ariadne::{Color, ColorGenerator, Label, Report};
-
+use crate::diagnostic::Diagnostic;
 use crate::lang_errors::{LangMessage, MsgBuilder};
 use crate::lexemes::tokens::TokenType;
 use crate::spans::{Span, Spanned};
@@ -13,62 +12,180 @@ pub enum ParseError {
         end_tag: Spanned<String>,
     },
     UnexpectedStreamEnd,
+    /// A `</>` closing an element whose rules do not allow generic end tags.
+    GenericEndNotAllowed {
+        element: String,
+        start_tag: Span,
+    },
+    /// A `/>` closing an element whose rules do not allow xml style self closing.
+    SelfClosingNotAllowed(String),
+    /// A raw element whose end tag was never found.
+    UnclosedRawElement(String),
+    ExpectedExpression(TokenType),
+    ExpectedValue(TokenType),
+    MissingProp {
+        element: String,
+        prop: String,
+    },
+    /// An `<else>` or `<else-if>` that does not follow an `<if>`.
+    MisplacedElse(String),
+    /// An `<empty>` that is not a direct child of a `<for>`.
+    MisplacedEmpty,
+    DuplicateEmpty {
+        first: Span,
+    },
+    /// The property should name a variable but it is not a valid identifier.
+    InvalidName(String),
+    /// A property set a second time in the same start tag, `first` is the name it was first set with.
+    DuplicateProp {
+        name: String,
+        first: Span,
+    },
+    /// A number literal that does not fit in its type, like an integer above `i64::MAX`.
+    InvalidNumber,
 }
 impl Spanned<ParseError> {
     fn unmatched_tag_error(
         &self,
         start_tag: &Spanned<String>,
         end_tag: &Spanned<String>,
-    ) -> Report<'_, Span> {
+    ) -> Diagnostic {
         let start_tag_name = &start_tag.item;
         let end_tag_name = &end_tag.item;
-        let builder = MsgBuilder::build_err(
-            format!("The end tag '{end_tag_name}' does not match the start tag '{end_tag_name}'",),
-            end_tag.span,
+        MsgBuilder::build_err(
+            format!("The end tag '{end_tag_name}' does not match the start tag '{start_tag_name}'"),
+            self.span,
         )
-        .get_inner();
-
-        builder
-            .with_label(
-                Label::new(start_tag.span)
-                    .with_color(Color::Fixed(210))
-                    .with_message("This tag"),
-            )
-            .with_label(
-                Label::new(end_tag.span)
-                    .with_color(Color::Fixed(210))
-                    .with_message("And this tag"),
-            )
-            .with_label(
-                Label::new(self.span)
-                    .with_color(Color::Red)
-                    .with_message("These tags should match."),
-            )
-            .with_help(format!("Rename '{end_tag_name}' to '{start_tag_name}'."))
-            .finish()
+        .with_code("T0102")
+        .with_label(start_tag.span, "This tag")
+        .with_label(end_tag.span, "And this tag")
+        .with_err_label("These tags should match.")
+        .with_help(format!("Rename '{end_tag_name}' to '{start_tag_name}'."))
+        .finish()
     }
 }
 impl LangMessage for Spanned<ParseError> {
-    fn msg(&'_ self) -> Report<'_, Span> {
+    fn diagnostic(&self) -> Diagnostic {
         use ParseError as Pe;
         match &self.item {
             Pe::InvalidToken(expected, got) => {
                 MsgBuilder::build_err(format!("Invalid Token '{got:?}'"), self.span)
+                    .with_code("T0103")
                     .with_err_label(format!("Expected this token to be {expected:?}."))
                     .finish()
             }
             Pe::UnmatchedTag { start_tag, end_tag } => self.unmatched_tag_error(start_tag, end_tag),
             Pe::UnexpectedToken(got) => {
                 MsgBuilder::build_err(format!("Unexpected token '{got:?}'"), self.span)
+                    .with_code("T0101")
                     .with_err_label("This should not be here.")
                     .finish()
             }
             Pe::UnexpectedStreamEnd => {
                 MsgBuilder::build_err("Unexpected end of token stream", self.span)
+                    .with_code("T0104")
                     .with_err_label("Expected more tokens here.")
                     .finish()
             }
-            Pe::Unspecified(err) => MsgBuilder::build_unspecified_err(err.to_string(), self.span),
+            Pe::GenericEndNotAllowed { element, start_tag } => MsgBuilder::build_err(
+                format!("'{element}' can not be closed with a generic end tag"),
+                self.span,
+            )
+            .with_code("T0105")
+            .with_err_label("The schema does not allow '</>' for this element.")
+            .with_help(format!("Close it with '</{element}>' instead."))
+            .with_label(*start_tag, "The element starts here.")
+            .finish(),
+            Pe::SelfClosingNotAllowed(element) => {
+                MsgBuilder::build_err(format!("'{element}' can not close itself"), self.span)
+                    .with_code("T0106")
+                    .with_err_label("The schema does not allow '/>' for this element.")
+                    .with_help(format!(
+                        "Write an explicit closing tag like '<{element}></{element}>'."
+                    ))
+                    .finish()
+            }
+            Pe::UnclosedRawElement(name) => {
+                MsgBuilder::build_err(format!("Raw element '{name}' is never closed"), self.span)
+                    .with_code("T0107")
+                    .with_err_label("The content of this element runs until the end of the file.")
+                    .with_help(format!("Close it with '</{name}>' or '</>'."))
+                    .with_note(
+                        "Tags inside of a raw element are not parsed, so they can not close it.",
+                    )
+                    .finish()
+            }
+            Pe::ExpectedExpression(got) => {
+                MsgBuilder::build_err(format!("Expected an expression, got '{got:?}'"), self.span)
+                    .with_code("T0108")
+                    .with_err_label("An expression should start here.")
+                    .finish()
+            }
+            Pe::ExpectedValue(got) => {
+                MsgBuilder::build_err(format!("Expected a value, got '{got:?}'"), self.span)
+                    .with_code("T0109")
+                    .with_err_label("This is not a valid property value.")
+                    .with_help("Wrap expressions in braces like `name={value}`.")
+                    .finish()
+            }
+            Pe::MissingProp { element, prop } => MsgBuilder::build_err(
+                format!("Element '{element}' is missing the property '{prop}'"),
+                self.span,
+            )
+            .with_code("T0110")
+            .with_err_label(format!("Add '{prop}' to this tag."))
+            .finish(),
+            Pe::MisplacedElse(name) => {
+                MsgBuilder::build_err(format!("'{name}' without a matching 'if'"), self.span)
+                    .with_code("T0111")
+                    .with_err_label("This does not follow an 'if' or 'else-if'.")
+                    .with_help(format!(
+                        "Move '{name}' right after the '</if>' or '</else-if>' it belongs to."
+                    ))
+                    .finish()
+            }
+            Pe::MisplacedEmpty => {
+                MsgBuilder::build_err("'empty' outside of a 'for' loop", self.span)
+                    .with_code("T0112")
+                    .with_err_label("This is not a direct child of a 'for'.")
+                    .with_help("Move it inside the 'for' it is the fallback of.")
+                    .finish()
+            }
+            Pe::DuplicateEmpty { first } => {
+                MsgBuilder::build_err("A 'for' loop can only have one 'empty'", self.span)
+                    .with_code("T0113")
+                    .with_err_label("This is the second one.")
+                    .with_label(*first, "The first one is here.")
+                    .finish()
+            }
+            Pe::InvalidName(prop) => {
+                MsgBuilder::build_err(format!("Invalid variable name for '{prop}'"), self.span)
+                    .with_code("T0114")
+                    .with_err_label("This should be a string like \"item\".")
+                    .with_note("Names start with a letter or '_' and contain no spaces.")
+                    .finish()
+            }
+            Pe::DuplicateProp { name, first } => {
+                MsgBuilder::build_err(format!("The property '{name}' is set twice"), self.span)
+                    .with_code("T0115")
+                    .with_err_label("It is set again here.")
+                    .with_label(*first, "It is first set here.")
+                    .with_help(format!("Remove one of the '{name}' properties."))
+                    .finish()
+            }
+            Pe::InvalidNumber => MsgBuilder::build_err("Number out of range", self.span)
+                .with_code("T0116")
+                .with_err_label("This number is too large.")
+                .with_note(format!(
+                    "Integers go from {} to {}, write larger numbers with a decimal point.",
+                    i64::MIN,
+                    i64::MAX
+                ))
+                .finish(),
+            Pe::Unspecified(err) => MsgBuilder::build_err(err, self.span)
+                .with_code("T0100")
+                .with_err_label("On this expression")
+                .finish(),
         }
     }
 }
@@ -0,0 +1,239 @@
+use crate::{
+    ast::expr::{BinaryOp, Expr, SAFE_FILTER, UnaryOp},
+    lexemes::tokens::*,
+    spans::{IntoSpanned, Span, Spanned},
+};
+
+use super::{ParseError, Parser, Result, err};
+
+/// Binding power of the ternary operator, it binds the loosest and is right associative.
+const TERNARY_POWER: (u8, u8) = (2, 1);
+/// Left binding power of filters, they bind looser than anything so they apply to everything before them.
+const FILTER_POWER: u8 = 0;
+/// Binding power of prefix operators like `!` and `-`.
+const PREFIX_POWER: u8 = 17;
+
+enum Infix {
+    Binary(BinaryOp),
+    Ternary,
+    Filter,
+}
+impl<'input> Parser<'input> {
+    /// Parses an expression wrapped in braces like `{user.name}`.
+    ///
+    /// Returns the expression and the span including the braces.
+    pub(super) fn parse_braced_expr(&mut self) -> Result<(Spanned<Expr>, Spanned<()>)> {
+        let restore = self.tokens.lexes_unsignificant();
+        self.significant_only();
+        let start = self.consume(TokenType::LBrace)?;
+        let expr = self.parse_expression()?;
+        let end = self.consume(TokenType::RBrace)?;
+        self.tokens.toggle_unsignificant(restore);
+        Ok((expr, ().to_spanned(start.span + end.span)))
+    }
+    pub(super) fn parse_expression(&mut self) -> Result<Spanned<Expr>> {
+        self.parse_binding(0)
+    }
+    /// Pratt parser loop, parses operators that bind tighter than `min_power`.
+    fn parse_binding(&mut self, min_power: u8) -> Result<Spanned<Expr>> {
+        let mut lhs = self.parse_prefix()?;
+        while let Some((infix, op_span)) = self.peek_infix()? {
+            let (left_power, right_power) = match &infix {
+                Infix::Binary(op) => op.binding_power(),
+                Infix::Ternary => TERNARY_POWER,
+                Infix::Filter => (FILTER_POWER, FILTER_POWER),
+            };
+            if left_power < min_power {
+                break;
+            }
+            self.skip_to(op_span)?;
+            lhs = match infix {
+                Infix::Binary(op) => {
+                    let rhs = self.parse_binding(right_power)?;
+                    let span = lhs.span + rhs.span;
+                    Expr::Binary {
+                        op: op.to_spanned(op_span),
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    }
+                    .to_spanned(span)
+                }
+                Infix::Ternary => {
+                    let then = self.parse_expression()?;
+                    self.consume(TokenType::Colon)?;
+                    let otherwise = self.parse_binding(right_power)?;
+                    let span = lhs.span + otherwise.span;
+                    Expr::Ternary {
+                        cond: Box::new(lhs),
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    }
+                    .to_spanned(span)
+                }
+                Infix::Filter => self.parse_filter(lhs)?,
+            };
+        }
+        Ok(lhs)
+    }
+    /// Parses the filter after a pipe like `truncate(20, "...")`, the parentheses are optional without arguments.
+    fn parse_filter(&mut self, value: Spanned<Expr>) -> Result<Spanned<Expr>> {
+        let name = self.expect(TokenType::Word)?;
+        self.next()?;
+        let mut args = vec![];
+        let mut span = value.span + name.span;
+        let has_args = self.peek()?.is(TokenType::LParen);
+        let name_text = self.text(&name);
+        if name_text == SAFE_FILTER && !has_args {
+            return Ok(Expr::Safe(Box::new(value)).to_spanned(span));
+        }
+        if has_args {
+            self.next()?;
+            while !self.peek_some()?.is(TokenType::RParen) {
+                args.push(self.parse_expression()?);
+                if !self.peek_some()?.is(TokenType::Comma) {
+                    break;
+                }
+                self.next()?;
+            }
+            span = span + self.consume(TokenType::RParen)?.span;
+        }
+        Ok(Expr::Filter {
+            value: Box::new(value),
+            name: name_text.to_spanned(name.span),
+            args,
+        }
+        .to_spanned(span))
+    }
+    /// Consumes tokens until the end of `span`.
+    fn skip_to(&mut self, span: Span) -> Result<()> {
+        while self.peek_some()?.span.end <= span.end {
+            self.next()?;
+        }
+        Ok(())
+    }
+    /// Peeks the infix operator at the current token.
+    ///
+    /// Operators like `==` or `&&` are lexed as two tokens, they are only joined
+    /// when nothing separates them.
+    fn peek_infix(&mut self) -> Result<Option<(Infix, Span)>> {
+        use TokenType as T;
+        let token = self.peek()?;
+        // Only lexed when it can be joined, what follows the closing `}` of raw content is not a token
+        let joinable = [
+            T::Equal,
+            T::Bang,
+            T::Lesser,
+            T::Greater,
+            T::Ampersand,
+            T::Pipe,
+            T::Question,
+        ];
+        let next = if token.is_any(joinable) {
+            self.peek_next()?
+        } else {
+            token.clone()
+        };
+        let joined = next.span.start == token.span.end;
+        let pair = |kind: TokenType| joined && next.is(kind);
+        let op = match token.kind {
+            T::Plus => BinaryOp::Add,
+            T::Minus => BinaryOp::Sub,
+            T::Star => BinaryOp::Mul,
+            T::Slash => BinaryOp::Div,
+            T::Percent => BinaryOp::Rem,
+            T::Equal if pair(T::Equal) => BinaryOp::Eq,
+            T::Bang if pair(T::Equal) => BinaryOp::NotEq,
+            T::Lesser if pair(T::Equal) => BinaryOp::LesserEq,
+            T::Lesser => BinaryOp::Lesser,
+            T::Greater if pair(T::Equal) => BinaryOp::GreaterEq,
+            T::Greater => BinaryOp::Greater,
+            T::Ampersand if pair(T::Ampersand) => BinaryOp::And,
+            T::Pipe if pair(T::Pipe) => BinaryOp::Or,
+            T::Pipe => return Ok(Some((Infix::Filter, token.span))),
+            T::Question if pair(T::Question) => BinaryOp::Coalesce,
+            T::Question => return Ok(Some((Infix::Ternary, token.span))),
+            _ => return Ok(None),
+        };
+        let span = match op {
+            BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::LesserEq
+            | BinaryOp::GreaterEq
+            | BinaryOp::And
+            | BinaryOp::Or
+            | BinaryOp::Coalesce => token.span + next.span,
+            _ => token.span,
+        };
+        Ok(Some((Infix::Binary(op), span)))
+    }
+    fn parse_prefix(&mut self) -> Result<Spanned<Expr>> {
+        let token = self.peek_some()?;
+        let op = match token.kind {
+            TokenType::Bang => UnaryOp::Not,
+            TokenType::Minus => UnaryOp::Neg,
+            _ => return self.parse_postfix(),
+        };
+        self.next()?;
+        let expr = self.parse_binding(PREFIX_POWER)?;
+        let span = token.span + expr.span;
+        Ok(Expr::Unary {
+            op: op.to_spanned(token.span),
+            expr: Box::new(expr),
+        }
+        .to_spanned(span))
+    }
+    /// Parses member access and indexing like `a.b[c]`
+    fn parse_postfix(&mut self) -> Result<Spanned<Expr>> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            let token = self.peek()?;
+            match token.kind {
+                TokenType::Dot => {
+                    self.next()?;
+                    let name = self.expect(TokenType::Word)?;
+                    self.next()?;
+                    let field = self.text(&name).to_spanned(name.span);
+                    let span = expr.span + name.span;
+                    expr = Expr::Member {
+                        object: Box::new(expr),
+                        field,
+                    }
+                    .to_spanned(span);
+                }
+                TokenType::LBracket => {
+                    self.next()?;
+                    let index = self.parse_expression()?;
+                    let end = self.consume(TokenType::RBracket)?;
+                    let span = expr.span + end.span;
+                    expr = Expr::Index {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                    .to_spanned(span);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+    fn parse_primary(&mut self) -> Result<Spanned<Expr>> {
+        let token = self.peek_some()?;
+        let expr = match token.kind {
+            TokenType::Word => Expr::Variable(self.text(&token)),
+            TokenType::True
+            | TokenType::False
+            | TokenType::Null
+            | TokenType::Int
+            | TokenType::Float
+            | TokenType::Str(_) => Expr::Literal(self.parse_literal(&token)?),
+            TokenType::LParen => {
+                self.next()?;
+                let inner = self.parse_expression()?;
+                let end = self.consume(TokenType::RParen)?;
+                return Ok(inner.item.to_spanned(token.span + end.span));
+            }
+            _ => return err(ParseError::ExpectedExpression(token.kind).to_spanned(token.span)),
+        };
+        self.next()?;
+        Ok(expr.to_spanned(token.span))
+    }
+}
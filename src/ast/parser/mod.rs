@@ -1,28 +1,32 @@
-use std::{
-    clone,
-    collections::HashMap,
-    error::Error,
-    fs::File,
-    io::{self, BufReader},
-    os::raw,
-    path::Path,
-};
+use std::{num::IntErrorKind, path::Path};
+
+use indexmap::IndexMap;
 
 use crate::{
     ast::{nodes::*, schema::ElementSchema},
+    filestore::FileStore,
     lang_errors::{LangError, LangMessage, LangResult},
-    lexemes::{lexer::Lexer, tokens::*},
-    spans::{FileID, IntoSpanned, Span, Spanned},
+    lexemes::{
+        lexer::{LexError, Lexer},
+        tokens::*,
+    },
+    render::escape::is_raw_text_element,
+    spans::{FileID, IntoSpanned, Span, SpanText, Spanned},
 };
 
+mod control;
 mod error;
+mod expr;
 use error::*;
 #[derive(Debug)]
 pub struct Parser<'input> {
     file_id: FileID,
-    input: &'input str,
+    input: SpanText<'input>,
     tokens: Lexer<'input>,
     pub schema: ElementSchema,
+    /// Whether errors are collected in `errors` and skipped instead of stopping the parse.
+    recovering: bool,
+    errors: Vec<LangError>,
 }
 pub type Result<T = Spanned<Node>> = LangResult<T>;
 fn err<T>(value: impl LangMessage + 'static) -> Result<T> {
@@ -31,21 +35,36 @@ fn err<T>(value: impl LangMessage + 'static) -> Result<T> {
 impl<'input> Parser<'input> {
     /// converts token spans into text
     fn text(&mut self, token: &Token) -> String {
-        self.input[token.span.start..token.span.end].to_string()
+        self.input.get(token.span).to_string()
     }
 
-    fn parse_int(&mut self, token: &Token) -> Value {
-        let mut text = self.input[token.span.start..token.span.end].to_string();
+    /// Parses an integer literal, one that does not fit in an `i64` is out of range.
+    fn parse_int(&mut self, token: &Token) -> Result<Value> {
+        let mut text = self.input.get(token.span).to_string();
         let idk: Vec<_> = text.chars().filter(|c| c != &'_').collect();
         text = String::from_iter(idk);
-        Value::Int(text.parse().unwrap())
+        match text.parse::<i64>() {
+            Ok(num) => Ok(Value::Int(num)),
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                ) =>
+            {
+                err(ParseError::InvalidNumber.to_spanned(token.span))
+            }
+            Err(_) => err(LexError::InvalidNumber.to_spanned(token.span)),
+        }
     }
 
-    fn parse_float(&mut self, token: &Token) -> Value {
-        let mut text = self.input[token.span.start..token.span.end].to_string();
+    fn parse_float(&mut self, token: &Token) -> Result<Value> {
+        let mut text = self.input.get(token.span).to_string();
         let idk: Vec<_> = text.chars().filter(|c| c != &'_').collect();
         text = String::from_iter(idk);
-        Value::Float(text.parse().unwrap())
+        match text.parse() {
+            Ok(num) => Ok(Value::Float(num)),
+            Err(_) => err(LexError::InvalidNumber.to_spanned(token.span)),
+        }
     }
     /// peeks the current token
     fn peek(&mut self) -> Result<Token> {
@@ -72,40 +91,16 @@ impl<'input> Parser<'input> {
         }
         Ok(peeked)
     }
-    /// advances to the next meaningful token
-    ///
-    fn next_significant(&mut self) -> Result<Token> {
-        self.skip_unsignificant()?;
-        return Ok(self.next()?);
-    }
-    fn skip_unsignificant(&mut self) -> Result<Vec<Token>> {
-        let mut buffer: Vec<Token> = vec![];
-        if self.peek()?.is_significant() {
-            return Ok(buffer);
-        }
-        while let Some(token) = self.peek_opt()? {
-            if token.is_significant() {
-                return Ok(buffer);
-            }
-            buffer.push(self.next()?);
-        }
-        Ok(buffer)
-    }
     fn significant_only(&mut self) {
         self.tokens.toggle_unsignificant(false);
     }
 
     fn allow_unsignificant(&mut self) {
-        self.tokens.toggle_unsignificant(false);
+        self.tokens.toggle_unsignificant(true);
     }
     fn next(&mut self) -> Result<Token> {
         self.tokens.next()
     }
-    fn expect_next(&mut self) -> Result<Token> {
-        let token = self.peek_some()?;
-        self.next()?;
-        Ok(token)
-    }
 
     /// checks if a token is the expected token and if it isnt returns an error
     /// this is used for checking if certain expressions are valid
@@ -122,13 +117,6 @@ impl<'input> Parser<'input> {
         self.check_valid(expected, token.clone())?;
         Ok(token)
     }
-    fn is_expected(&mut self, expected: TokenType) -> Result<Option<Token>> {
-        let token = self.peek()?;
-        if token.is(&expected) {
-            return Ok(Some(token));
-        }
-        Ok(None)
-    }
     fn consume(&mut self, expected: TokenType) -> Result<Token> {
         let token = self.expect(expected)?;
         self.next()?;
@@ -140,53 +128,97 @@ impl<'input> Parser<'input> {
         self.next()?;
         Ok(self.text(&token))
     }
-    pub fn parse_raw_content(&mut self) -> Result {
+    /// Reads the content of the raw element `name` verbatim, up to its `</name>` or a generic `</>`.
+    ///
+    /// Nothing inside is interpreted, so comments and tags in it are kept as they are.
+    /// Only `{}` interpolations are parsed, and only when `interpolate` is set.
+    ///
+    /// Scripts and stylesheets use braces themselves, in them a `{` followed by whitespace
+    /// or not starting a valid interpolation is kept as it is.
+    fn parse_raw_content(
+        &mut self,
+        name: &str,
+        start_tag_span: Span,
+        interpolate: bool,
+    ) -> Result<Vec<Spanned<Node>>> {
+        let mut nodes = vec![];
+        let mut start = self.tokens.index;
+        let closer = format!("</{name}");
+        let keeps_braces = is_raw_text_element(name);
         let mut buffer = String::new();
-        let start_index = self.tokens.index;
-        while let Some(ch) = self.tokens.peek_char() {
-            if ch != '<' {
-                buffer.push(ch);
-                self.tokens.advance();
-                continue;
+        loop {
+            let rest = self.tokens.rest();
+            let closes = rest.starts_with("</>")
+                || rest
+                    .strip_prefix(&closer)
+                    .is_some_and(|after| after.trim_start().starts_with('>'));
+            if closes {
+                break;
             }
-            dbg!(self.peek());
+            if interpolate && rest.starts_with('{') {
+                let before = self.tokens.clone();
+                let spaced = rest[1..].starts_with(char::is_whitespace);
+                match self.parse_interpolation() {
+                    Ok(interpolation) if !(keeps_braces && spaced) => {
+                        if !buffer.is_empty() {
+                            let span = Span::new(self.file_id, start, before.index);
+                            nodes.push(Node::Raw(std::mem::take(&mut buffer)).to_spanned(span));
+                        }
+                        nodes.push(interpolation);
+                        start = self.tokens.index;
+                        continue;
+                    }
+                    Err(error) if !keeps_braces => return Err(error),
+                    _ => self.tokens = before,
+                }
+            }
+            let Some(ch) = self.tokens.advance() else {
+                let error = ParseError::UnclosedRawElement(name.to_owned());
+                return err(error.to_spanned(start_tag_span));
+            };
+            buffer.push(ch);
         }
-        let end_index = self.tokens.index;
-        let span = Span::new(self.file_id, start_index, end_index);
-        let node = Node::Text(buffer).to_spanned(span);
-
-        Ok(node)
+        if !buffer.is_empty() {
+            let span = Span::new(self.file_id, start, self.tokens.index);
+            nodes.push(Node::Raw(buffer).to_spanned(span));
+        }
+        Ok(nodes)
     }
     pub fn parse_text(&mut self) -> Result {
         let mut buffer = String::new();
         let start_span = self.peek()?.span;
-        let mut end_span = start_span.clone();
+        let mut end_span = start_span;
         while let Some(token) = self.peek_opt()? {
             if !token.exists() {
                 break;
             }
             if token.is(&TokenType::Comment) {
-                continue;
+                break;
             }
             let text = self.text(&token);
             buffer += &text;
             let advanced = self.next()?;
             end_span = advanced.span;
-            if self
-                .peek()?
-                .is_any(&[TokenType::Lesser, TokenType::End, TokenType::LCloser])
-            {
+            if self.peek()?.is_any(&[
+                TokenType::Lesser,
+                TokenType::End,
+                TokenType::LCloser,
+                TokenType::Comment,
+                TokenType::LBrace,
+            ]) {
                 break;
             }
         }
-        buffer = buffer.trim().to_owned();
-        return Ok(Node::Text(buffer).to_spanned(start_span + end_span));
+        Ok(Node::Text(buffer).to_spanned(start_span + end_span))
     }
     /// Parses element properties like `a = 1`
-    fn parse_props(&mut self) -> Result<HashMap<String, Spanned<Value>>> {
-        let mut props: HashMap<String, Spanned<Value>> = HashMap::new();
+    /// Parses the properties of a start tag, in the order they are written.
+    ///
+    /// A property set twice is an error, when recovering it is reported and the first value is kept.
+    fn parse_props(&mut self) -> Result<IndexMap<String, Prop>> {
+        let mut props: IndexMap<String, Prop> = IndexMap::new();
 
-        while let Some(_) = self.peek_opt()? {
+        while self.peek_opt()?.is_some() {
             let token = self.peek()?;
             if token.is(TokenType::RCloser) {
                 break;
@@ -195,39 +227,105 @@ impl<'input> Parser<'input> {
                 break;
             }
 
-            let prop_name = self.consume_word()?;
-            //self.skip_unsignificant()?;
+            let name = self.consume(TokenType::Word)?;
+            let prop_name = self.text(&name);
             let sign = self.peek()?;
-            if sign.isnt(&TokenType::Equal) {
-                props.insert(prop_name, Value::Bool(true).to_spanned(sign.span));
+            let value = if sign.isnt(&TokenType::Equal) {
+                Value::Bool(true).to_spanned(name.span)
+            } else {
+                self.next()?;
+                self.parse_value()?
+            };
+            if let Some(first) = props.get(&prop_name) {
+                let error = ParseError::DuplicateProp {
+                    name: prop_name,
+                    first: first.name_span,
+                }
+                .to_spanned(name.span);
+                if !self.recovering {
+                    return err(error);
+                }
+                self.errors.push(error.into());
                 continue;
             }
-            self.next()?;
-            //self.skip_unsignificant()?;
-            let value_token = self.peek()?;
-            let value = self.parse_value(&value_token)?;
-            self.next()?;
-            props.insert(prop_name, value);
+            let prop = Prop {
+                name_span: name.span,
+                value,
+            };
+            props.insert(prop_name, prop);
         }
 
-        return Ok(props);
+        Ok(props)
     }
-    fn parse_content(&mut self, raw: bool) -> Result<Vec<Spanned<Node>>> {
+    /// Parses the children of the element named `parent`, or of the template root when `None`.
+    fn parse_content(&mut self, parent: Option<&str>) -> Result<Vec<Spanned<Node>>> {
         let mut children: Vec<Spanned<Node>> = vec![];
-        while let Some(token) = self.peek_opt()? {
-            if token.is_any([TokenType::End, TokenType::LCloser]) || !token.exists() {
-                break;
+        loop {
+            self.allow_unsignificant();
+            let start = self.tokens.index;
+            match self.parse_child(parent) {
+                Ok(Some(parsed)) => children.push(parsed),
+                Ok(None) => break,
+                Err(error) if self.recovering => children.push(self.recover(error, start)),
+                Err(error) => return Err(error),
             }
-
-            let parsed = self.parse_expr(raw)?;
-            if let Node::Text(ref text) = parsed.item {
-                if text == "" {
-                    continue;
-                }
+        }
+        let children = trim_text(children);
+        if !self.recovering {
+            return self.fold_control_flow(children, parent);
+        }
+        match self.fold_control_flow(children.clone(), parent) {
+            Ok(folded) => Ok(folded),
+            Err(error) => {
+                self.errors.push(error);
+                Ok(children)
             }
-            children.push(parsed);
         }
-        Ok(children)
+    }
+    /// Parses the next child of the element named `parent`, or `None` when its content ends.
+    fn parse_child(&mut self, parent: Option<&str>) -> Result<Option<Spanned<Node>>> {
+        let Some(token) = self.peek_opt()? else {
+            return Ok(None);
+        };
+        if token.is_any([TokenType::End, TokenType::LCloser]) || !token.exists() {
+            return Ok(None);
+        }
+        if let Some(parent) = parent
+            && self.is_closed_by(parent, &token)?
+        {
+            return Ok(None);
+        }
+        self.parse_expr().map(Some)
+    }
+    /// Whether `token` starts an element that closes the element `parent`, whose end tag is optional.
+    fn is_closed_by(&mut self, parent: &str, token: &Token) -> Result<bool> {
+        if token.isnt(&TokenType::Lesser) {
+            return Ok(false);
+        }
+        let name = self.peek_next()?;
+        if name.isnt(&TokenType::Word) {
+            return Ok(false);
+        }
+        let name = self.text(&name);
+        Ok(self
+            .schema
+            .get_rule(parent)
+            .is_some_and(|rules| rules.optional_end && rules.closed_by.contains(&name)))
+    }
+    /// Whether the element `tag_name`, whose end tag is optional, ends before the current token.
+    ///
+    /// That is the case for the start of an element closing it, the end tag of
+    /// another element and the end of the input.
+    fn ends_implicitly(&mut self, tag_name: &str) -> Result<bool> {
+        let token = self.peek()?;
+        if !token.exists() || token.is(&TokenType::Lesser) {
+            return Ok(true);
+        }
+        if token.isnt(&TokenType::LCloser) {
+            return Ok(false);
+        }
+        let name = self.peek_next()?;
+        Ok(name.is(&TokenType::Word) && self.text(&name) != tag_name)
     }
 }
 impl<'input> Parser<'input> {
@@ -241,17 +339,25 @@ impl<'input> Parser<'input> {
         let start = self.next()?;
         let tag_name = self.consume_word()?;
 
-        let parse_raw = if let Some(rules) = self.schema.get_rule(&tag_name) {
-            rules.parse_raw
-        } else {
-            false
-        };
+        let rules = self.schema.get_rule(&tag_name);
+        let parse_raw = rules.is_some_and(|rules| rules.parse_raw);
+        let escapable_raw = rules.is_some_and(|rules| rules.escapable_raw);
+        let void = rules.is_some_and(|rules| rules.void);
+        let optional_end = rules.is_some_and(|rules| rules.optional_end);
 
         if self.peek()?.is(TokenType::RCloser) {
             return self.handle_empty_element(start.span, tag_name);
         }
 
-        let props = self.parse_props()?;
+        let props = match self.parse_props() {
+            Ok(props) => props,
+            // Errors in the start tag do not lose the element when its end can be found
+            Err(error) if self.recovering && self.skip_to_tag_end() => {
+                self.errors.push(error);
+                IndexMap::new()
+            }
+            Err(error) => return Err(error),
+        };
 
         if let Some(end) = self.peek()?.matches(TokenType::RCloser) {
             self.next()?;
@@ -263,16 +369,34 @@ impl<'input> Parser<'input> {
             start.span + token.span
         };
         self.allow_unsignificant();
-        let children = self.parse_content(parse_raw)?;
+        if void {
+            return Ok(ElementBuilder::new(&tag_name, start_tag_span)
+                .with_props(props)
+                .finish_node(start_tag_span));
+        }
+        let children = if parse_raw {
+            self.parse_raw_content(&tag_name, start_tag_span, escapable_raw)?
+        } else {
+            self.parse_content(Some(&tag_name))?
+        };
+        if optional_end && self.ends_implicitly(&tag_name)? {
+            let span = children
+                .last()
+                .map_or(start_tag_span, |child| start.span + child.span);
+            return Ok(ElementBuilder::new(tag_name, start_tag_span)
+                .with_props(props)
+                .with_children(children)
+                .finish_node(span));
+        }
         let end_start = self.next()?;
-        return self.finish_element(
+        self.finish_element(
             tag_name,
             start.span,
             start_tag_span,
             props,
             children,
             end_start,
-        );
+        )
     }
 
     fn handle_immediate_greater(&mut self) -> Result {
@@ -285,6 +409,7 @@ impl<'input> Parser<'input> {
 
     fn handle_empty_element(&mut self, start_span: Span, tag_name: String) -> Result {
         let end = self.next()?;
+        self.check_self_closing(&tag_name, end.span)?;
         let element = ElementBuilder::new(&tag_name, start_span + end.span)
             .finish_node(start_span + end.span);
         Ok(element)
@@ -294,30 +419,48 @@ impl<'input> Parser<'input> {
         &mut self,
         start_span: Span,
         tag_name: String,
-        props: HashMap<String, Spanned<Value>>,
+        props: IndexMap<String, Prop>,
         end_span: Span,
     ) -> Result {
+        self.check_self_closing(&tag_name, end_span)?;
         let element = ElementBuilder::new(&tag_name, start_span + end_span)
             .with_props(props)
             .finish_node(start_span + end_span);
         Ok(element)
     }
 
+    /// Errors if the element `tag_name`, closed by the `/>` at `end_span`, is not allowed to close itself.
+    fn check_self_closing(&self, tag_name: &str, end_span: Span) -> Result<()> {
+        match self.schema.get_rule(tag_name) {
+            Some(rules) if !rules.allow_xml => {
+                err(ParseError::SelfClosingNotAllowed(tag_name.to_owned()).to_spanned(end_span))
+            }
+            _ => Ok(()),
+        }
+    }
     fn finish_element(
         &mut self,
         tag_name: String,
         start_span: Span,
         start_tag_span: Span,
-        props: HashMap<String, Spanned<Value>>,
+        props: IndexMap<String, Prop>,
         children: Vec<Spanned<Node>>,
         end_start: Token,
     ) -> Result {
         self.significant_only();
+        // Elements still open at the end of the input have no end tag
+        let kind = if end_start.is(TokenType::End) {
+            EndTag::Generic
+        } else {
+            EndTag::None
+        };
+        let mut end_tag = (kind, end_start.span);
         if end_start.is(TokenType::LCloser) {
             let end_tagname = self.consume_word()?;
 
             let end = self.consume(TokenType::Greater)?;
             let end_tag_span = end_start.span + end.span;
+            end_tag = (EndTag::Named, end_tag_span);
             if end_tagname != tag_name {
                 let error = ParseError::UnmatchedTag {
                     start_tag: tag_name.to_spanned(start_tag_span),
@@ -325,34 +468,65 @@ impl<'input> Parser<'input> {
                 };
                 return err(error.to_spanned(start_span + end.span));
             }
+        } else if end_start.is(TokenType::End)
+            && self
+                .schema
+                .get_rule(&tag_name)
+                .is_some_and(|rules| !rules.allow_generic_end)
+        {
+            let error = ParseError::GenericEndNotAllowed {
+                element: tag_name,
+                start_tag: start_tag_span,
+            };
+            return err(error.to_spanned(end_start.span));
         }
         let element = ElementBuilder::new(tag_name, start_tag_span)
             .with_props(props)
             .with_children(children)
-            .with_end_tag_span(end_start.span)
-            .finish_node(start_span + end_start.span);
+            .with_end_tag(end_tag.0, end_tag.1)
+            .finish_node(start_span + end_tag.1);
 
         Ok(element)
     }
 }
 impl<'input> Parser<'input> {
-    fn parse_value(&mut self, token: &Token) -> Result<Spanned<Value>> {
-        let value: Result<Value> = match &token.kind {
+    /// Converts a literal token into its [`Value`].
+    fn parse_literal(&mut self, token: &Token) -> Result<Value> {
+        match &token.kind {
             TokenType::True => Ok(Value::Bool(true)),
             TokenType::False => Ok(Value::Bool(false)),
             TokenType::Null => Ok(Value::Null),
-            TokenType::Float => Ok(self.parse_float(token)),
-            TokenType::Int => Ok(self.parse_int(token)),
+            TokenType::Float => self.parse_float(token),
+            TokenType::Int => self.parse_int(token),
             TokenType::Str(txt) => Ok(Value::String(txt.to_string())),
-            foo => {
-                dbg!(foo);
-                todo!()
+            other => unreachable!("{other:?} is not a literal"),
+        }
+    }
+    /// Parses a property value, either a literal or an expression in braces.
+    fn parse_value(&mut self) -> Result<Spanned<Value>> {
+        let token = self.peek_some()?;
+        match &token.kind {
+            TokenType::True
+            | TokenType::False
+            | TokenType::Null
+            | TokenType::Float
+            | TokenType::Int
+            | TokenType::Str(_) => {
+                self.next()?;
+                Ok(self.parse_literal(&token)?.to_spanned(token.span))
             }
-        };
-        let value = value?;
-        Ok(value.to_spanned(token.span))
+            TokenType::LBrace => {
+                let (expr, braces) = self.parse_braced_expr()?;
+                Ok(Value::Expr(Box::new(expr)).to_spanned(braces.span))
+            }
+            other => err(ParseError::ExpectedValue(other.clone()).to_spanned(token.span)),
+        }
+    }
+    fn parse_interpolation(&mut self) -> Result {
+        let (expr, braces) = self.parse_braced_expr()?;
+        Ok(Node::Interpolation(expr).to_spanned(braces.span))
     }
-    fn parse_expr(&mut self, raw: bool) -> Result {
+    fn parse_expr(&mut self) -> Result {
         let peeked = self.peek()?;
 
         match peeked.kind {
@@ -360,10 +534,10 @@ impl<'input> Parser<'input> {
             TokenType::Comment => {
                 let text = self.text(&peeked);
                 self.next()?;
-                return Ok(Node::Comment(text).to_spanned(peeked.span));
+                Ok(Node::Comment(text).to_spanned(peeked.span))
             }
 
-            _ if raw => self.parse_raw_content(),
+            TokenType::LBrace => self.parse_interpolation(),
             _ => self.parse_text(),
         }
     }
@@ -373,32 +547,319 @@ impl<'input> Parser<'input> {
     pub fn make(input: &'input str, file_id: FileID, schema: ElementSchema) -> Self {
         Parser {
             file_id,
-            input,
+            input: SpanText::new(input),
             tokens: Lexer::new(input, file_id),
             schema,
+            recovering: false,
+            errors: vec![],
         }
     }
 
     pub fn new(input: &'input str, file_id: FileID) -> Self {
         Parser {
             file_id,
-            input,
-            schema: ElementSchema::new(),
+            input: SpanText::new(input),
+            schema: ElementSchema::html5(),
             tokens: Lexer::new(input, file_id),
+            recovering: false,
+            errors: vec![],
         }
     }
     pub fn with_schema(self, schema: ElementSchema) -> Self {
         Self { schema, ..self }
     }
-    pub fn with_schema_file(self, path: impl AsRef<Path>) -> Result<Self> {
-        let schema = ElementSchema::from_file(path)?;
-        Ok(Self { schema, ..self })
+    /// Merges the rules of the schema file at `path` on top of the current ones,
+    /// the files read are added to `file_store`.
+    pub fn with_schema_file(
+        mut self,
+        path: impl AsRef<Path>,
+        file_store: &mut FileStore,
+    ) -> Result<Self> {
+        self.schema.merge_file(path, file_store)?;
+        Ok(self)
     }
     pub fn parse(&mut self) -> Result<Vec<Spanned<Node>>> {
-        self.parse_content(false)
+        self.parse_content(None)
+    }
+    /// Parses the whole input without stopping at the first error.
+    ///
+    /// Returns the nodes that could be parsed, with a [`Node::Error`] in place of each
+    /// part that could not, along with every error that was found.
+    pub fn parse_recovering(&mut self) -> (Vec<Spanned<Node>>, Vec<LangError>) {
+        self.recovering = true;
+        let mut nodes = vec![];
+        loop {
+            match self.parse_content(None) {
+                Ok(mut parsed) => nodes.append(&mut parsed),
+                Err(error) => self.errors.push(error),
+            }
+            // The content of the root only ends early at an end tag that closes nothing
+            let start = self.tokens.index;
+            let error = match self.peek() {
+                Ok(token) if !token.exists() => break,
+                Ok(token) => ParseError::UnexpectedToken(token.kind)
+                    .to_spanned(token.span)
+                    .into(),
+                Err(error) => error,
+            };
+            nodes.push(self.recover(error, start));
+        }
+        self.recovering = false;
+        (nodes, std::mem::take(&mut self.errors))
+    }
+    /// Records `error` and skips to where parsing can resume, returning the placeholder
+    /// for the content from `start` that was skipped.
+    fn recover(&mut self, error: LangError, start: usize) -> Spanned<Node> {
+        self.errors.push(error);
+        if self.tokens.index == start {
+            self.tokens.advance();
+        }
+        self.resynchronise();
+        let span = Span::new(self.file_id, start, self.tokens.index);
+        Node::Error.to_spanned(span)
+    }
+    /// Skips to the next `<` or `</`, or past the next `>`.
+    fn resynchronise(&mut self) {
+        while let Some(ch) = self.tokens.peek_char() {
+            if ch == '<' {
+                return;
+            }
+            self.tokens.advance();
+            if ch == '>' {
+                return;
+            }
+        }
+    }
+    /// Skips to the `>` or `/>` ending the current start tag, if there is one before the next tag.
+    fn skip_to_tag_end(&mut self) -> bool {
+        let rest = self.tokens.rest();
+        let Some(end) = rest.find(['<', '>']) else {
+            return false;
+        };
+        if !rest[end..].starts_with('>') {
+            return false;
+        }
+        let skipped = rest[..end].strip_suffix('/').unwrap_or(&rest[..end]);
+        for _ in skipped.chars() {
+            self.tokens.advance();
+        }
+        true
     }
 }
+/// Removes the whitespace used for indenting the markup.
+///
+/// Only whitespace runs at the edge of a text node that contain a line break are dropped,
+/// spaces written between values on the same line are kept.
+fn trim_text(children: Vec<Spanned<Node>>) -> Vec<Spanned<Node>> {
+    let mut trimmed = Vec::with_capacity(children.len());
+    for mut child in children {
+        let Node::Text(text) = &mut child.item else {
+            trimmed.push(child);
+            continue;
+        };
+        let start = text.len() - text.trim_start().len();
+        let mut new_text = if text[..start].contains('\n') {
+            text.trim_start()
+        } else {
+            text.as_str()
+        };
+        let end = new_text.trim_end().len();
+        if new_text[end..].contains('\n') {
+            new_text = new_text.trim_end();
+        }
+        if new_text.is_empty() {
+            continue;
+        }
+        *text = new_text.to_owned();
+        trimmed.push(child);
+    }
+    trimmed
+}
 pub fn parse(input: &str, file_id: FileID, schema: ElementSchema) -> Result<Vec<Spanned<Node>>> {
     let mut parser = Parser::new(input, file_id).with_schema(schema);
-    parser.parse_content(false)
+    parser.parse_content(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        Compiler,
+        ast::nodes::{Node, Value},
+        diagnostic::Diagnostic,
+        filestore::FileStore,
+        lang_errors::LangError,
+        spans::Spanned,
+    };
+
+    fn render(template: &str, data: serde_json::Value) -> String {
+        Compiler::make(FileStore::new(), true)
+            .render(template, &data)
+            .expect("The template should render")
+    }
+    fn parse_error(template: &str) -> Diagnostic {
+        let error = Compiler::make(FileStore::new(), true)
+            .parse(template)
+            .expect_err("The template should not parse");
+        let LangError::Compiler(msg) = error else {
+            panic!("Expected a compiler error, got {error}");
+        };
+        msg.diagnostic()
+    }
+
+    #[test]
+    fn keeps_spaces_between_values() {
+        let template = r#"<for each={xs} as="x" index="i">{i}:{x} </for>"#;
+        assert_eq!(render(template, json!({"xs": [1, 2, 3]})), "0:1 1:2 2:3 ");
+    }
+
+    #[test]
+    fn drops_indentation() {
+        let template = "<ul>\n    <li>a</li>\n    <li>b</li>\n</ul>\n";
+        assert_eq!(render(template, json!({})), "<ul><li>a</li><li>b</li></ul>");
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let template = "{1 + 2 * 3} {(1 + 2) * 3} {-2 * 3 + 1} {1 < 2 && 2 < 1 || true}";
+        assert_eq!(render(template, json!({})), "7 9 -5 true");
+    }
+
+    #[test]
+    fn rejects_numbers_out_of_range() {
+        let diagnostic = parse_error("{9999999999999999999999}");
+        assert_eq!(diagnostic.code.as_deref(), Some("T0116"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (1, 23));
+        let diagnostic = parse_error("<a x=99999999999999999999></a>");
+        assert_eq!(diagnostic.code.as_deref(), Some("T0116"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (5, 25));
+    }
+
+    #[test]
+    fn large_floats_parse() {
+        assert_eq!(render("{9999999999999999999999.0}", json!({})), "1e+22");
+    }
+
+    #[test]
+    fn rejects_duplicate_props() {
+        let diagnostic = parse_error(r#"<a href="x" href="y"></a>"#);
+        assert_eq!(diagnostic.code.as_deref(), Some("T0115"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (12, 16));
+        let first = &diagnostic.labels[1];
+        assert_eq!((first.span.start, first.span.end), (3, 7));
+    }
+
+    #[test]
+    fn keeps_props_in_source_order() {
+        let template = r#"<a z="1" href="/" b={2} hidden></a>"#;
+        assert_eq!(
+            render(template, json!({})),
+            r#"<a z="1" href="/" b="2" hidden></a>"#
+        );
+    }
+
+    #[test]
+    fn props_keep_their_name_span() {
+        let nodes = Compiler::make(FileStore::new(), true)
+            .parse(r#"<a href="x" hidden></a>"#)
+            .unwrap();
+        let Node::Element(element) = &nodes[0].item else {
+            panic!("Expected an element");
+        };
+        let hidden = &element.props["hidden"];
+        assert_eq!((hidden.name_span.start, hidden.name_span.end), (12, 18));
+        assert_eq!((hidden.value.span.start, hidden.value.span.end), (12, 18));
+        let href = &element.props["href"];
+        assert_eq!((href.name_span.start, href.name_span.end), (3, 7));
+    }
+    fn parse_recovering(template: &str) -> (Vec<Spanned<Node>>, Vec<Diagnostic>) {
+        let (nodes, errors) = Compiler::make(FileStore::new(), true).parse_recovering(template);
+        let diagnostics = errors
+            .into_iter()
+            .map(|error| match error {
+                LangError::Compiler(msg) => msg.diagnostic(),
+                other => panic!("Expected a compiler error, got {other}"),
+            })
+            .collect();
+        (nodes, diagnostics)
+    }
+
+    #[test]
+    fn recovering_collects_every_error() {
+        let (nodes, errors) = parse_recovering(r#"<p>{1 +}</p><a x=></a><div>ok</div>"#);
+        let codes: Vec<_> = errors.iter().map(|error| error.code.as_deref()).collect();
+        assert_eq!(codes, [Some("T0108"), Some("T0109")]);
+        let names: Vec<_> = nodes
+            .iter()
+            .map(|node| match &node.item {
+                Node::Element(element) => element.name.as_str(),
+                other => panic!("Expected an element, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(names, ["p", "a", "div"]);
+    }
+
+    #[test]
+    fn recovering_leaves_error_nodes() {
+        let (nodes, errors) = parse_recovering("</b><p>{)}</p>");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(nodes[0].item, Node::Error));
+        assert_eq!((nodes[0].span.start, nodes[0].span.end), (0, 4));
+        let Node::Element(element) = &nodes[1].item else {
+            panic!("Expected an element");
+        };
+        assert_eq!(element.children.len(), 1);
+        assert!(matches!(element.children[0].item, Node::Error));
+    }
+
+    #[test]
+    fn recovering_keeps_the_first_duplicate_prop() {
+        let (nodes, errors) = parse_recovering(r#"<a href="x" href="y">t</a>"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code.as_deref(), Some("T0115"));
+        let Node::Element(element) = &nodes[0].item else {
+            panic!("Expected an element");
+        };
+        assert_eq!(element.props.len(), 1);
+        assert!(matches!(&element.props["href"].value.item, Value::String(href) if href == "x"));
+    }
+
+    #[test]
+    fn subtraction_without_spaces() {
+        assert_eq!(render("{5-1} {10-2}", json!({})), "4 8");
+        assert_eq!(
+            render("{a*-1} {a - 1} {-1 - -2}", json!({"a": 3})),
+            "-3 2 1"
+        );
+    }
+
+    #[test]
+    fn reports_malformed_numbers() {
+        let diagnostic = parse_error("{1.5.5}");
+        assert_eq!(diagnostic.code.as_deref(), Some("T0004"));
+    }
+
+    #[test]
+    fn keeps_multibyte_text() {
+        assert_eq!(
+            render("<p>héllo wörld</p>", json!({})),
+            "<p>héllo wörld</p>"
+        );
+        assert_eq!(render("<p>éé x</p>", json!({})), "<p>éé x</p>");
+        let template = r#"<a title="ünï" href="/">{"ö"} {café}</a>"#;
+        assert_eq!(
+            render(template, json!({"café": 1})),
+            r#"<a title="ünï" href="/">ö 1</a>"#
+        );
+    }
+
+    #[test]
+    fn interpolates_after_multibyte_text() {
+        let template = "<p>héllo {x}, ünï {y}</p>";
+        assert_eq!(
+            render(template, json!({"x": "wörld", "y": 2})),
+            "<p>héllo wörld, ünï 2</p>"
+        );
+    }
 }
@@ -0,0 +1,43 @@
+use crate::{
+    diagnostic::Diagnostic,
+    lang_errors::{LangMessage, MsgBuilder},
+    spans::*,
+};
+#[derive(Debug, Clone)]
+pub enum SchemaError {
+    /// The file is not a valid schema, holds the reason serde gave.
+    Invalid(String),
+    /// A schema listed in `"$extends"` could not be read.
+    NotFound { path: String, reason: String },
+    /// The schema ends up extending itself, holds the chain of files that lead back to it.
+    Cycle(Vec<String>),
+}
+impl LangMessage for Spanned<SchemaError> {
+    fn diagnostic(&self) -> Diagnostic {
+        use SchemaError as Se;
+        match &self.item {
+            Se::Invalid(reason) => MsgBuilder::build_err("Invalid schema", self.span)
+                .with_code("T0501")
+                .with_err_label(reason)
+                .with_note(
+                    "A schema maps element names or patterns like 'svg:*' to the rules of those elements.",
+                )
+                .finish(),
+            Se::NotFound { path, reason } => {
+                MsgBuilder::build_err(format!("Could not load the schema '{path}'"), self.span)
+                    .with_code("T0502")
+                    .with_err_label(reason)
+                    .with_note("Paths are relative to the directory of the schema extending them.")
+                    .finish()
+            }
+            Se::Cycle(chain) => {
+                let first = chain.first().map(String::as_str).unwrap_or_default();
+                MsgBuilder::build_err(format!("'{first}' ends up extending itself"), self.span)
+                    .with_code("T0503")
+                    .with_err_label("This starts the cycle.")
+                    .with_note(format!("The chain is {}.", chain.join(" -> ")))
+                    .finish()
+            }
+        }
+    }
+}
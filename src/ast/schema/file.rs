@@ -0,0 +1,508 @@
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings, json_schema};
+use serde::{
+    Deserialize, Deserializer,
+    de::{
+        self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+        value::MapAccessDeserializer,
+    },
+};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::lint::LintLevels,
+    filestore::{FileStore, cycle_chain},
+    lang_errors::{LangError, LangMessage, LangResult},
+    spans::{FileID, IntoSpanned, Span, Spanned},
+};
+
+use super::{ElementSchema, PartialRules, RULE_FIELDS, SchemaError};
+
+/// Key of a schema file listing the schema files it extends, it can not clash with an element name.
+pub const EXTENDS_KEY: &str = "$extends";
+/// Key of a schema file setting the levels of lints by their name.
+pub const LINTS_KEY: &str = "$lints";
+
+fn err<T>(value: impl LangMessage + 'static) -> LangResult<T> {
+    Err(value.into())
+}
+/// The formats schema files can be written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+impl SchemaFormat {
+    /// The format of the file at `path` by its extension, json when it has none of the known ones.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let extension = path.as_ref().extension().and_then(|ext| ext.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+    /// Deserializes `text` written in this format.
+    pub fn parse<T: DeserializeOwned>(self, text: &str) -> LangResult<T> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(text)?),
+            Self::Toml => toml::from_str(text).map_err(|error| LangError::from_toml(error, text)),
+            Self::Yaml => Ok(serde_yaml::from_str(text)?),
+        }
+    }
+}
+/// The content of a schema file, the rules it sets in order, the levels of lints and the files it extends.
+struct SchemaFile {
+    extends: Vec<String>,
+    rules: Vec<(String, PartialRules)>,
+    lints: LintLevels,
+}
+impl<'de> Deserialize<'de> for SchemaFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(SchemaFileVisitor { strict: false })
+    }
+}
+impl JsonSchema for SchemaFile {
+    fn schema_name() -> Cow<'static, str> {
+        "ElementSchema".into()
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let rules = generator.subschema_for::<PartialRules>();
+        let lints = generator.subschema_for::<LintLevels>();
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            EXTENDS_KEY.to_owned(),
+            serde_json::json!({
+                "description": "Schema files whose rules these ones are merged on top of, relative to this one.",
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } },
+                ],
+            }),
+        );
+        properties.insert(LINTS_KEY.to_owned(), lints.to_value());
+        json_schema!({
+            "description": "The rules of elements by their name, or by a prefix followed by `*` like `svg:*`.",
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": rules,
+        })
+    }
+}
+/// A [`SchemaFile`] whose rules can not have unknown fields.
+struct StrictSchemaFile(SchemaFile);
+impl<'de> Deserialize<'de> for StrictSchemaFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let file = deserializer.deserialize_map(SchemaFileVisitor { strict: true })?;
+        Ok(Self(file))
+    }
+}
+struct SchemaFileVisitor {
+    strict: bool,
+}
+impl<'de> Visitor<'de> for SchemaFileVisitor {
+    type Value = SchemaFile;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of element names to their rules")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut file = SchemaFile {
+            extends: vec![],
+            rules: vec![],
+            lints: LintLevels::default(),
+        };
+        while let Some(key) = map.next_key::<String>()? {
+            if key == EXTENDS_KEY {
+                file.extends = map.next_value::<Paths>()?.0;
+            } else if key == LINTS_KEY {
+                file.lints = map.next_value()?;
+            } else if self.strict {
+                file.rules.push((key, map.next_value_seed(StrictRules)?));
+            } else {
+                file.rules.push((key, map.next_value()?));
+            }
+        }
+        Ok(file)
+    }
+}
+/// Deserializes [`PartialRules`], failing at the first field they do not have.
+struct StrictRules;
+impl<'de> DeserializeSeed<'de> for StrictRules {
+    type Value = PartialRules;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+impl<'de> Visitor<'de> for StrictRules {
+    type Value = PartialRules;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the rules of an element")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        PartialRules::deserialize(MapAccessDeserializer::new(KnownFields(map)))
+    }
+}
+/// Passes the entries of a map through, failing at the first key that is not in [`RULE_FIELDS`].
+struct KnownFields<A>(A);
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for KnownFields<A> {
+    type Error = A::Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(key) = self.0.next_key::<String>()? else {
+            return Ok(None);
+        };
+        if !RULE_FIELDS.contains(&key.as_str()) {
+            return Err(de::Error::unknown_field(&key, RULE_FIELDS));
+        }
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.next_value_seed(seed)
+    }
+}
+/// A single path or a list of them.
+struct Paths(Vec<String>);
+impl<'de> Deserialize<'de> for Paths {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PathsVisitor)
+    }
+}
+struct PathsVisitor;
+impl<'de> Visitor<'de> for PathsVisitor {
+    type Value = Paths;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a path or a list of paths")
+    }
+    fn visit_str<E: serde::de::Error>(self, path: &str) -> Result<Self::Value, E> {
+        Ok(Paths(vec![path.to_owned()]))
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut paths = vec![];
+        while let Some(path) = seq.next_element()? {
+            paths.push(path);
+        }
+        Ok(Paths(paths))
+    }
+}
+impl ElementSchema {
+    /// The json schema of schema files, editors can use it to check and complete them.
+    pub fn json_schema() -> JsonValue {
+        SchemaSettings::default()
+            .for_deserialize()
+            .into_generator()
+            .into_root_schema_for::<SchemaFile>()
+            .to_value()
+    }
+    /// Reads the schema file at `path`, see [`ElementSchema::merge_file`].
+    pub fn from_file(path: impl AsRef<Path>, file_store: &mut FileStore) -> LangResult<Self> {
+        let mut schema = Self::new();
+        schema.merge_file(path, file_store)?;
+        Ok(schema)
+    }
+    /// Merges the rules of the schema file at `path` on top of these ones,
+    /// after the rules of the files it lists in `"$extends"`.
+    ///
+    /// The format of every file is told by its extension, see [`SchemaFormat::from_path`].
+    /// Every file read is added to `file_store`, so errors are reported against it.
+    pub fn merge_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        file_store: &mut FileStore,
+    ) -> LangResult<()> {
+        SchemaLoader::new(file_store).merge_file(self, path)
+    }
+    /// Like [`ElementSchema::merge_file`], but the file at `path` is read as `format`
+    /// whatever its extension is. The files it extends are still told by their extension.
+    pub fn merge_file_as(
+        &mut self,
+        path: impl AsRef<Path>,
+        format: SchemaFormat,
+        file_store: &mut FileStore,
+    ) -> LangResult<()> {
+        SchemaLoader::new(file_store)
+            .with_format(format)
+            .merge_file(self, path)
+    }
+}
+/// Reads schema files along with the ones they extend.
+pub struct SchemaLoader<'a> {
+    file_store: &'a mut FileStore,
+    /// Canonical paths of the schemas currently being loaded, used to detect cycles.
+    stack: Vec<PathBuf>,
+    /// Format of the file that is loaded, told by its extension when unset.
+    format: Option<SchemaFormat>,
+    /// Whether unknown fields in the rules of an element are an error instead of being ignored.
+    strict: bool,
+}
+impl<'a> SchemaLoader<'a> {
+    /// Makes a loader adding every file it reads to `file_store`, so errors are reported against it.
+    pub fn new(file_store: &'a mut FileStore) -> Self {
+        Self {
+            file_store,
+            stack: vec![],
+            format: None,
+            strict: false,
+        }
+    }
+    /// Reads the file that is loaded as `format` whatever its extension is,
+    /// the files it extends are still told by their extension.
+    pub fn with_format(self, format: SchemaFormat) -> Self {
+        Self {
+            format: Some(format),
+            ..self
+        }
+    }
+    /// Makes unknown fields in the rules of an element an error, so misspelled ones are caught.
+    pub fn with_strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+    /// Merges the rules of the schema file at `path` on top of the ones of `schema`,
+    /// see [`ElementSchema::merge_file`].
+    pub fn merge_file(
+        &mut self,
+        schema: &mut ElementSchema,
+        path: impl AsRef<Path>,
+    ) -> LangResult<()> {
+        let path = path.as_ref();
+        let canonical = fs::canonicalize(path)?;
+        let text = fs::read_to_string(path)?;
+        let format = self.format.unwrap_or_else(|| SchemaFormat::from_path(path));
+        self.load_source(schema, text, path, format, canonical)
+    }
+    fn load_source(
+        &mut self,
+        schema: &mut ElementSchema,
+        text: String,
+        path: &Path,
+        format: SchemaFormat,
+        canonical: PathBuf,
+    ) -> LangResult<()> {
+        let file_id = self
+            .file_store
+            .add_named(text.clone(), path.display().to_string());
+        let file = if self.strict {
+            format.parse::<StrictSchemaFile>(&text).map(|file| file.0)
+        } else {
+            format.parse::<SchemaFile>(&text)
+        };
+        let file = match file {
+            Ok(file) => file,
+            Err(error) => return err(invalid(&text, file_id, error)),
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        self.stack.push(canonical);
+        for parent in &file.extends {
+            let span = extends_span(&text, file_id, parent, format);
+            let parent_path = dir.join(parent);
+            let not_found = |error: std::io::Error| {
+                SchemaError::NotFound {
+                    path: parent_path.display().to_string(),
+                    reason: error.to_string(),
+                }
+                .to_spanned(span)
+            };
+            let canonical = fs::canonicalize(&parent_path).map_err(not_found)?;
+            if let Some(chain) = cycle_chain(&self.stack, &canonical) {
+                return err(SchemaError::Cycle(chain).to_spanned(span));
+            }
+            let text = fs::read_to_string(&parent_path).map_err(not_found)?;
+            let format = SchemaFormat::from_path(&parent_path);
+            self.load_source(schema, text, &parent_path, format, canonical)?;
+        }
+        self.stack.pop();
+        schema.merge(file.rules);
+        schema.lints.merge(file.lints.0);
+        Ok(())
+    }
+}
+/// Converts a deserialization error into one pointing at the location in `text` it happened at.
+fn invalid(text: &str, file_id: FileID, error: LangError) -> Spanned<SchemaError> {
+    let (line, column) = error.location().unwrap_or((1, 1));
+    let reason = match error {
+        LangError::Toml { message, .. } | LangError::Yaml { message, .. } => message,
+        other => {
+            let message = other.to_string();
+            let location = format!(" at line {line} column {column}");
+            message
+                .strip_suffix(&location)
+                .unwrap_or(&message)
+                .to_owned()
+        }
+    };
+    let span = location_span(text, file_id, line, column);
+    SchemaError::Invalid(reason).to_spanned(span)
+}
+/// The span of the character at `line` and `column` of `text`, both counted from 1 like serde does.
+fn location_span(text: &str, file_id: FileID, line: usize, column: usize) -> Span {
+    let mut start = 0;
+    for (index, line_text) in text.split_inclusive('\n').enumerate() {
+        if index + 1 == line {
+            start += line_text
+                .char_indices()
+                .take_while(|(byte, _)| *byte + 1 < column)
+                .count();
+            break;
+        }
+        start += line_text.chars().count();
+    }
+    let end = (start + 1).min(text.chars().count());
+    Span::new(file_id, start.min(end), end)
+}
+/// The span of `path` in the `$extends` of `text`, or of the start of the file if it can not be found.
+fn extends_span(text: &str, file_id: FileID, path: &str, format: SchemaFormat) -> Span {
+    let after_key = written_forms(EXTENDS_KEY, format)
+        .iter()
+        .filter_map(|key| text.find(key.as_str()).map(|start| start + key.len()))
+        .min();
+    let found = after_key.and_then(|after| {
+        written_forms(path, format)
+            .into_iter()
+            .filter_map(|written| {
+                let offset = text[after..].find(&written)?;
+                Some((after + offset, written.chars().count()))
+            })
+            .min()
+    });
+    let Some((byte, len)) = found else {
+        return Span::new(file_id, 0, 0);
+    };
+    let start = text[..byte].chars().count();
+    Span::new(file_id, start, start + len)
+}
+/// The ways the string `value` can be written in `format`.
+fn written_forms(value: &str, format: SchemaFormat) -> Vec<String> {
+    let double_quoted = serde_json::to_string(value).unwrap_or_default();
+    let single_quoted = format!("'{value}'");
+    match format {
+        SchemaFormat::Json => vec![double_quoted],
+        SchemaFormat::Toml => vec![double_quoted, single_quoted],
+        SchemaFormat::Yaml => vec![double_quoted, single_quoted, value.to_owned()],
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Compiler, diagnostic::Diagnostic, filestore::FileStore, lang_errors::LangError};
+
+    fn load(name: &str, schema: &str, strict: bool) -> Result<Compiler, LangError> {
+        let dir = std::env::temp_dir().join(format!("schema-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, schema).unwrap();
+        let compiler = Compiler::make(FileStore::new(), true);
+        let compiler = if strict {
+            compiler.with_strict_schema_file(&path)
+        } else {
+            compiler.with_schema_file(&path)
+        };
+        fs::remove_file(path).unwrap();
+        compiler
+    }
+    fn load_strict(name: &str, schema: &str) -> Result<Compiler, LangError> {
+        load(name, schema, true)
+    }
+    fn diagnostic(error: LangError) -> Diagnostic {
+        let LangError::Compiler(msg) = error else {
+            panic!("Expected a compiler error, got {error}");
+        };
+        msg.diagnostic()
+    }
+    fn invalid_reason(error: LangError) -> String {
+        let diagnostic = diagnostic(error);
+        assert_eq!(diagnostic.code.as_deref(), Some("T0501"));
+        diagnostic.labels[0].message.clone()
+    }
+
+    #[test]
+    fn accepts_known_fields() {
+        let schema = r#"{"a": {"void": false, "attributes": {"href": {"type": "string", "required": true}}}}"#;
+        assert!(load_strict("known.json", schema).is_ok());
+    }
+    #[test]
+    fn rejects_unknown_rule_fields() {
+        let error = load_strict("rules.json", r#"{"a": {"viod": true}}"#)
+            .err()
+            .expect("'viod' is not a rule");
+        assert!(invalid_reason(error).contains("viod"));
+    }
+    #[test]
+    fn rejects_unknown_attribute_fields() {
+        let schema = r#"{"a": {"attributes": {"href": {"requird": true}}}}"#;
+        let error = load_strict("attributes.json", schema)
+            .err()
+            .expect("'requird' is not an attribute rule");
+        assert!(invalid_reason(error).contains("requird"));
+    }
+    #[test]
+    fn rejects_unknown_attribute_fields_in_every_format() {
+        let toml = "[a.attributes.href]\nrequird = true\n";
+        let yaml = "a:\n  attributes:\n    href:\n      requird: true\n";
+        for (name, schema) in [("attributes.toml", toml), ("attributes.yaml", yaml)] {
+            let error = load_strict(name, schema)
+                .err()
+                .expect("'requird' is not an attribute rule");
+            assert!(invalid_reason(error).contains("requird"), "{name}");
+        }
+    }
+    #[test]
+    fn reports_missing_parents_at_their_path() {
+        let schemas = [
+            (
+                "extends.json",
+                r#"{"$extends": ["missing.json"]}"#,
+                "\"missing.json\"",
+            ),
+            (
+                "extends.toml",
+                "\"$extends\" = 'missing.toml'\n",
+                "'missing.toml'",
+            ),
+            (
+                "extends.yaml",
+                "$extends:\n  - missing.yaml\n",
+                "missing.yaml",
+            ),
+        ];
+        for (name, schema, written) in schemas {
+            let error = load(name, schema, false)
+                .err()
+                .expect("The parent does not exist");
+            let diagnostic = diagnostic(error);
+            assert_eq!(diagnostic.code.as_deref(), Some("T0502"), "{name}");
+            let start = schema.find(written).unwrap();
+            let span = (diagnostic.span.start, diagnostic.span.end);
+            assert_eq!(span, (start, start + written.len()), "{name}");
+        }
+    }
+    #[test]
+    fn reports_cycles() {
+        let dir = std::env::temp_dir().join(format!("schema-cycle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.json"), r#"{"$extends": "b.yaml"}"#).unwrap();
+        fs::write(dir.join("b.yaml"), "$extends: a.json\n").unwrap();
+        let error = Compiler::make(FileStore::new(), true)
+            .with_schema_file(dir.join("a.json"))
+            .err()
+            .expect("The schemas extend each other");
+        let diagnostic = diagnostic(error);
+        assert_eq!(diagnostic.code.as_deref(), Some("T0503"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (10, 16));
+        let chain = diagnostic.notes[0].clone();
+        assert!(chain.ends_with("a.json."), "{chain}");
+        assert_eq!(chain.matches(" -> ").count(), 2, "{chain}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,418 @@
+use std::{collections::HashMap, fmt::Display};
+
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::{lint::LintLevels, nodes::Value},
+    render::escape::VOID_ELEMENTS,
+};
+
+mod error;
+mod file;
+pub use error::*;
+pub use file::{EXTENDS_KEY, LINTS_KEY, SchemaFormat, SchemaLoader};
+
+/// Elements whose content is not html, read verbatim by the [`ElementSchema::html5`] preset.
+pub const HTML_RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+/// Elements html5 made obsolete, the [`ElementSchema::html5`] preset marks them deprecated.
+pub const HTML_DEPRECATED_ELEMENTS: &[&str] = &[
+    "acronym",
+    "applet",
+    "basefont",
+    "big",
+    "blink",
+    "center",
+    "dir",
+    "font",
+    "frame",
+    "frameset",
+    "marquee",
+    "nobr",
+    "noframes",
+    "plaintext",
+    "strike",
+    "tt",
+    "xmp",
+];
+/// Elements that start a new block, they close an open `<p>`.
+const HTML_BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+/// Elements whose end tag is optional, with the elements whose start closes them.
+///
+/// `<p>` is closed by the elements in [`HTML_BLOCK_ELEMENTS`].
+const HTML_OPTIONAL_END_ELEMENTS: &[(&str, &[&str])] = &[
+    ("li", &["li"]),
+    ("dt", &["dt", "dd"]),
+    ("dd", &["dt", "dd"]),
+    ("option", &["option", "optgroup"]),
+    ("optgroup", &["optgroup"]),
+    ("rt", &["rt", "rp"]),
+    ("rp", &["rt", "rp"]),
+    ("thead", &["tbody", "tfoot"]),
+    ("tbody", &["tbody", "tfoot"]),
+    ("tfoot", &[]),
+    ("tr", &["tr", "tbody", "tfoot"]),
+    ("td", &["td", "th", "tr", "tbody", "tfoot"]),
+    ("th", &["td", "th", "tr", "tbody", "tfoot"]),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementRules {
+    #[serde(default = "truev")]
+    pub allow_generic_end: bool,
+    #[serde(default = "falsev", alias = "parse_content_raw")]
+    pub parse_raw: bool,
+    /// Keeps `{}` interpolations in raw content, like the text of `<title>` and `<textarea>`.
+    #[serde(default = "falsev")]
+    pub escapable_raw: bool,
+    #[serde(default = "truev", alias = "allow_xml_construction")]
+    pub allow_xml: bool,
+    /// The attributes the element accepts by name, any attribute is accepted when unset.
+    #[serde(default)]
+    pub attributes: Option<HashMap<String, AttributeRules>>,
+    /// What kind of content the element can have.
+    #[serde(default)]
+    pub content: ContentModel,
+    /// Names of the elements allowed as children, any element is allowed when unset.
+    #[serde(default)]
+    pub children: Option<Vec<String>>,
+    /// The element has no content and no end tag, like `<br>`.
+    #[serde(default = "falsev")]
+    pub void: bool,
+    /// The end tag can be left out, the element is then closed by the end of its parent
+    /// or by the start of one of the elements in `closed_by`.
+    #[serde(default = "falsev")]
+    pub optional_end: bool,
+    #[serde(default)]
+    pub closed_by: Vec<String>,
+    /// Using the element is reported by the `deprecated-elements` lint.
+    #[serde(default = "falsev")]
+    pub deprecated: bool,
+}
+impl Default for ElementRules {
+    fn default() -> Self {
+        Self {
+            allow_generic_end: true,
+            parse_raw: false,
+            escapable_raw: false,
+            allow_xml: true,
+            attributes: None,
+            content: ContentModel::Any,
+            children: None,
+            void: false,
+            optional_end: false,
+            closed_by: vec![],
+            deprecated: false,
+        }
+    }
+}
+/// The fields of [`PartialRules`], with the other names they can be written with.
+pub const RULE_FIELDS: &[&str] = &[
+    "allow_generic_end",
+    "parse_raw",
+    "parse_content_raw",
+    "escapable_raw",
+    "allow_xml",
+    "allow_xml_construction",
+    "attributes",
+    "content",
+    "children",
+    "void",
+    "optional_end",
+    "closed_by",
+    "deprecated",
+];
+/// Other names fields of [`PartialRules`] can be written with, and the field they stand for.
+const RULE_ALIASES: &[(&str, &str)] = &[
+    ("parse_content_raw", "parse_raw"),
+    ("allow_xml_construction", "allow_xml"),
+];
+/// The rules of an element as written in a schema file that is merged into another schema,
+/// the fields that are set replace the ones the element already had.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields, transform = add_rule_aliases)]
+pub struct PartialRules {
+    /// Whether the element can be closed with a generic `</>`.
+    pub allow_generic_end: Option<bool>,
+    /// Reads the content verbatim instead of parsing it.
+    #[serde(alias = "parse_content_raw")]
+    pub parse_raw: Option<bool>,
+    /// Keeps `{}` interpolations in raw content.
+    pub escapable_raw: Option<bool>,
+    /// Whether the element can close itself like `<name/>`.
+    #[serde(alias = "allow_xml_construction")]
+    pub allow_xml: Option<bool>,
+    /// The attributes the element accepts by name.
+    pub attributes: Option<HashMap<String, AttributeRules>>,
+    /// What kind of content the element can have.
+    pub content: Option<ContentModel>,
+    /// Names or patterns of the elements allowed as children.
+    pub children: Option<Vec<String>>,
+    /// The element has no content and no end tag, like `<br>`.
+    pub void: Option<bool>,
+    /// Whether the end tag can be left out.
+    pub optional_end: Option<bool>,
+    /// Elements whose start closes the element when its end tag is optional.
+    pub closed_by: Option<Vec<String>>,
+    /// Using the element is reported by the `deprecated-elements` lint.
+    pub deprecated: Option<bool>,
+}
+/// Lists the other names of the fields in the json schema of [`PartialRules`], so editors accept them.
+fn add_rule_aliases(schema: &mut Schema) {
+    let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(JsonValue::as_object_mut)
+    else {
+        return;
+    };
+    for (alias, field) in RULE_ALIASES {
+        if let Some(property) = properties.get(*field).cloned() {
+            properties.insert(alias.to_string(), property);
+        }
+    }
+}
+impl PartialRules {
+    /// Replaces the fields of `rules` that are set here.
+    pub fn apply(self, rules: &mut ElementRules) {
+        if let Some(allow_generic_end) = self.allow_generic_end {
+            rules.allow_generic_end = allow_generic_end;
+        }
+        if let Some(parse_raw) = self.parse_raw {
+            rules.parse_raw = parse_raw;
+        }
+        if let Some(escapable_raw) = self.escapable_raw {
+            rules.escapable_raw = escapable_raw;
+        }
+        if let Some(allow_xml) = self.allow_xml {
+            rules.allow_xml = allow_xml;
+        }
+        if let Some(attributes) = self.attributes {
+            rules.attributes = Some(attributes);
+        }
+        if let Some(content) = self.content {
+            rules.content = content;
+        }
+        if let Some(children) = self.children {
+            rules.children = Some(children);
+        }
+        if let Some(void) = self.void {
+            rules.void = void;
+        }
+        if let Some(optional_end) = self.optional_end {
+            rules.optional_end = optional_end;
+        }
+        if let Some(closed_by) = self.closed_by {
+            rules.closed_by = closed_by;
+        }
+        if let Some(deprecated) = self.deprecated {
+            rules.deprecated = deprecated;
+        }
+    }
+}
+/// The rules of an attribute, its fields are never ignored so a misspelled one is always an error.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AttributeRules {
+    /// The type of the values the attribute accepts.
+    #[serde(default, rename = "type")]
+    pub ty: AttributeType,
+    /// Whether the attribute has to be set.
+    #[serde(default)]
+    pub required: bool,
+    /// Value the attribute is given when it is missing.
+    #[serde(default)]
+    pub default: Option<JsonValue>,
+}
+/// The type of the values an attribute accepts, expressions are only known when rendering so they are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    #[default]
+    Any,
+    String,
+    Int,
+    /// Any number, integers included.
+    Float,
+    Bool,
+    Null,
+    /// One of a list of strings, written like `{"enum": ["a", "b"]}`.
+    Enum(Vec<String>),
+}
+impl AttributeType {
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Any, _) | (_, Value::Expr(_)) => true,
+            (Self::String, Value::String(_)) => true,
+            (Self::Int, Value::Int(_)) => true,
+            (Self::Float, Value::Int(_) | Value::Float(_)) => true,
+            (Self::Bool, Value::Bool(_)) => true,
+            (Self::Null, Value::Null) => true,
+            (Self::Enum(options), Value::String(text)) => options.contains(text),
+            _ => false,
+        }
+    }
+}
+impl Display for AttributeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "any"),
+            Self::String => write!(f, "string"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::Bool => write!(f, "bool"),
+            Self::Null => write!(f, "null"),
+            Self::Enum(options) => {
+                let options: Vec<String> = options.iter().map(|opt| format!("'{opt}'")).collect();
+                write!(f, "one of {}", options.join(", "))
+            }
+        }
+    }
+}
+/// The kind of content an element can have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentModel {
+    #[default]
+    Any,
+    /// No content at all.
+    Empty,
+    /// Text and interpolations but no elements.
+    Text,
+    /// Elements but no text other than whitespace.
+    Elements,
+}
+#[inline(always)]
+const fn truev() -> bool {
+    true
+}
+
+#[inline(always)]
+const fn falsev() -> bool {
+    false
+}
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ElementSchema {
+    /// The rules of elements by their name, or by a prefix followed by `*` like `svg:*`.
+    #[serde(flatten)]
+    pub rules: HashMap<String, ElementRules>,
+    /// The levels lints are set to, the ones missing are at their default level.
+    #[serde(rename = "$lints", default)]
+    pub lints: LintLevels,
+}
+impl From<HashMap<String, ElementRules>> for ElementSchema {
+    fn from(rules: HashMap<String, ElementRules>) -> Self {
+        Self {
+            rules,
+            lints: LintLevels::default(),
+        }
+    }
+}
+
+impl ElementSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The rules of html5: void elements, raw text elements, elements whose end tag is optional
+    /// and the obsolete elements, which are deprecated.
+    pub fn html5() -> Self {
+        let mut schema = Self::new();
+        for name in VOID_ELEMENTS {
+            let rules = ElementRules {
+                void: true,
+                content: ContentModel::Empty,
+                ..Default::default()
+            };
+            schema.rules.insert(name.to_string(), rules);
+        }
+        // All of them interpolate, values are escaped as javascript in scripts and as css in stylesheets
+        for name in HTML_RAW_TEXT_ELEMENTS {
+            let rules = ElementRules {
+                parse_raw: true,
+                escapable_raw: true,
+                ..Default::default()
+            };
+            schema.rules.insert(name.to_string(), rules);
+        }
+        let paragraph = ("p", HTML_BLOCK_ELEMENTS);
+        for (name, closed_by) in HTML_OPTIONAL_END_ELEMENTS.iter().chain([&paragraph]) {
+            let rules = ElementRules {
+                optional_end: true,
+                closed_by: closed_by.iter().map(|name| name.to_string()).collect(),
+                ..Default::default()
+            };
+            schema.rules.insert(name.to_string(), rules);
+        }
+        for name in HTML_DEPRECATED_ELEMENTS {
+            let rules = ElementRules {
+                deprecated: true,
+                ..Default::default()
+            };
+            schema.rules.insert(name.to_string(), rules);
+        }
+        schema
+    }
+    /// The rules of the element `name`, those of the longest pattern matching it when it has none of its own.
+    pub fn get_rule(&self, name: impl AsRef<str>) -> Option<&ElementRules> {
+        let name = name.as_ref();
+        self.rules.get(name).or_else(|| {
+            self.rules
+                .iter()
+                .filter(|(pattern, _)| pattern.ends_with('*') && matches_pattern(pattern, name))
+                .max_by_key(|(pattern, _)| pattern.len())
+                .map(|(_, rules)| rules)
+        })
+    }
+    pub fn has_element(&self, name: impl AsRef<str>) -> bool {
+        self.get_rule(name).is_some()
+    }
+    /// Merges `overrides` on top of these rules, in order.
+    ///
+    /// An element without rules of its own starts from the ones of the pattern matching it.
+    pub fn merge(&mut self, overrides: impl IntoIterator<Item = (String, PartialRules)>) {
+        for (name, partial) in overrides {
+            let mut rules = self.get_rule(&name).cloned().unwrap_or_default();
+            partial.apply(&mut rules);
+            self.rules.insert(name, rules);
+        }
+    }
+}
+/// Whether the element `name` matches `pattern`, which is a name or a prefix followed by `*` like `svg:*`.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    ast::schema::AttributeType,
+    diagnostic::Diagnostic,
+    lang_errors::{LangMessage, MsgBuilder},
+    spans::*,
+};
+#[derive(Debug, Clone)]
+pub enum ValidationError {
+    UnknownAttribute {
+        element: String,
+        attribute: String,
+    },
+    /// Reported at the name of the attribute, `value` is the span of its value.
+    AttributeType {
+        attribute: String,
+        expected: AttributeType,
+        /// Either the type of the value or the value itself for enums.
+        found: String,
+        value: Span,
+    },
+    MissingAttribute {
+        element: String,
+        attribute: String,
+    },
+    /// Content in an element whose content model is empty.
+    ContentNotAllowed {
+        element: String,
+    },
+    /// Text in an element that only allows elements.
+    TextNotAllowed {
+        element: String,
+    },
+    /// A child element that is not allowed, `allowed` is empty when no element is.
+    ChildNotAllowed {
+        element: String,
+        child: String,
+        allowed: Vec<String>,
+    },
+}
+impl LangMessage for Spanned<ValidationError> {
+    fn diagnostic(&self) -> Diagnostic {
+        use ValidationError as Ve;
+        match &self.item {
+            Ve::UnknownAttribute { element, attribute } => MsgBuilder::build_err(
+                format!("'{element}' has no attribute '{attribute}'"),
+                self.span,
+            )
+            .with_code("T0301")
+            .with_err_label("This attribute is not declared in the schema.")
+            .finish(),
+            Ve::AttributeType {
+                attribute,
+                expected,
+                found,
+                value,
+            } => MsgBuilder::build_err(
+                format!("Attribute '{attribute}' should be {expected}, got {found}"),
+                self.span,
+            )
+            .with_code("T0302")
+            .with_err_label(format!("The schema declares this attribute as {expected}."))
+            .with_label(*value, "This value is not allowed by the schema.")
+            .finish(),
+            Ve::MissingAttribute { element, attribute } => MsgBuilder::build_err(
+                format!("'{element}' is missing the attribute '{attribute}'"),
+                self.span,
+            )
+            .with_code("T0303")
+            .with_err_label(format!("Add a '{attribute}' attribute here."))
+            .with_note("The schema marks it as required.")
+            .finish(),
+            Ve::ContentNotAllowed { element } => {
+                MsgBuilder::build_err(format!("'{element}' can not have content"), self.span)
+                    .with_code("T0304")
+                    .with_err_label("This is not allowed here.")
+                    .with_help(format!("Close it right away with '<{element}/>'."))
+                    .finish()
+            }
+            Ve::TextNotAllowed { element } => {
+                MsgBuilder::build_err(format!("'{element}' can not contain text"), self.span)
+                    .with_code("T0305")
+                    .with_err_label("Only elements are allowed here.")
+                    .finish()
+            }
+            Ve::ChildNotAllowed {
+                element,
+                child,
+                allowed,
+            } => {
+                let msg = MsgBuilder::build_err(
+                    format!("'{child}' is not allowed inside of '{element}'"),
+                    self.span,
+                )
+                .with_code("T0306")
+                .with_err_label("This element is not allowed here.");
+                if allowed.is_empty() {
+                    msg.with_note(format!("'{element}' can only contain text."))
+                        .finish()
+                } else {
+                    let allowed: Vec<String> =
+                        allowed.iter().map(|name| format!("'{name}'")).collect();
+                    msg.with_note(format!("'{element}' can contain {}.", allowed.join(", ")))
+                        .finish()
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    ast::{
+        nodes::*,
+        schema::{AttributeType, ContentModel, ElementRules, ElementSchema, matches_pattern},
+    },
+    lang_errors::{LangMessage, LangResult},
+    spans::{IntoSpanned, Spanned},
+};
+
+mod error;
+pub use error::*;
+
+pub type Result<T = ()> = LangResult<T>;
+fn err<T>(value: impl LangMessage + 'static) -> Result<T> {
+    Err(value.into())
+}
+/// Checks the attributes and children of every element against the rules of `schema`.
+///
+/// Missing attributes that have a default in the schema are added to the elements.
+pub fn validate(nodes: &mut [Spanned<Node>], schema: &ElementSchema) -> Result {
+    for node in nodes {
+        if let Node::Element(element) = &mut node.item
+            && let Some(rules) = schema.get_rule(&element.name)
+        {
+            validate_attributes(element, rules)?;
+            validate_content(element, rules)?;
+        }
+        for children in node.item.children_mut() {
+            validate(children, schema)?;
+        }
+    }
+    Ok(())
+}
+fn validate_attributes(element: &mut Element, rules: &ElementRules) -> Result {
+    let Some(attributes) = &rules.attributes else {
+        return Ok(());
+    };
+    for (name, Prop { name_span, value }) in &element.props {
+        let Some(attribute) = attributes.get(name) else {
+            return err(ValidationError::UnknownAttribute {
+                element: element.name.clone(),
+                attribute: name.clone(),
+            }
+            .to_spanned(*name_span));
+        };
+        if !attribute.ty.accepts(value) {
+            let found = match &value.item {
+                Value::String(text) if matches!(attribute.ty, AttributeType::Enum(_)) => {
+                    format!("'{text}'")
+                }
+                other => format!("type '{}'", other.type_name()),
+            };
+            return err(ValidationError::AttributeType {
+                attribute: name.clone(),
+                expected: attribute.ty.clone(),
+                found,
+                value: value.span,
+            }
+            .to_spanned(*name_span));
+        }
+    }
+    for (name, attribute) in attributes {
+        if element.props.contains_key(name) {
+            continue;
+        }
+        if let Some(default) = attribute.default.as_ref().and_then(Value::from_json) {
+            let default = Prop {
+                name_span: element.start_tag_span,
+                value: default.to_spanned(element.start_tag_span),
+            };
+            element.props.insert(name.clone(), default);
+        } else if attribute.required {
+            return err(ValidationError::MissingAttribute {
+                element: element.name.clone(),
+                attribute: name.clone(),
+            }
+            .to_spanned(element.start_tag_span));
+        }
+    }
+    Ok(())
+}
+fn validate_content(element: &Element, rules: &ElementRules) -> Result {
+    let mut children = vec![];
+    direct_children(&element.children, &mut children);
+    for child in children {
+        let is_element = matches!(child.item, Node::Element(_));
+        let is_blank = matches!(&child.item, Node::Text(text) if text.trim().is_empty());
+        let violation = match (rules.content, &child.item) {
+            (ContentModel::Empty, _) if !is_blank => Some(ValidationError::ContentNotAllowed {
+                element: element.name.clone(),
+            }),
+            (ContentModel::Text, Node::Element(child)) => Some(ValidationError::ChildNotAllowed {
+                element: element.name.clone(),
+                child: child.name.clone(),
+                allowed: vec![],
+            }),
+            (ContentModel::Elements, _) if !is_element && !is_blank => {
+                Some(ValidationError::TextNotAllowed {
+                    element: element.name.clone(),
+                })
+            }
+            (_, Node::Element(child))
+                if rules.children.as_ref().is_some_and(|allowed| {
+                    !allowed
+                        .iter()
+                        .any(|pattern| matches_pattern(pattern, &child.name))
+                }) =>
+            {
+                Some(ValidationError::ChildNotAllowed {
+                    element: element.name.clone(),
+                    child: child.name.clone(),
+                    allowed: rules.children.clone().unwrap_or_default(),
+                })
+            }
+            _ => None,
+        };
+        if let Some(violation) = violation {
+            let span = match &child.item {
+                Node::Element(child) => child.start_tag_span,
+                _ => child.span,
+            };
+            return err(violation.to_spanned(span));
+        }
+    }
+    Ok(())
+}
+/// Collects the nodes that end up as direct children, looking through control flow and components.
+fn direct_children<'a>(nodes: &'a [Spanned<Node>], children: &mut Vec<&'a Spanned<Node>>) {
+    for node in nodes {
+        match &node.item {
+            Node::Comment(_) | Node::Error => {}
+            Node::Text(_) | Node::Raw(_) | Node::Interpolation(_) | Node::Element(_) => {
+                children.push(node)
+            }
+            Node::If { .. } | Node::For(_) | Node::Component(_) | Node::Slot(_) => {
+                for nested in node.item.children() {
+                    direct_children(nested, children);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        Compiler, ast::schema::ElementSchema, diagnostic::Diagnostic, filestore::FileStore,
+        lang_errors::LangError,
+    };
+
+    fn validation_error(template: &str) -> Diagnostic {
+        let schema: ElementSchema = serde_json::from_value(json!({
+            "a": {"attributes": {"href": {"type": "string"}}}
+        }))
+        .unwrap();
+        let error = Compiler::make(FileStore::new(), true)
+            .with_schema(schema)
+            .parse(template)
+            .expect_err("The template should not validate");
+        let LangError::Compiler(msg) = error else {
+            panic!("Expected a compiler error, got {error}");
+        };
+        msg.diagnostic()
+    }
+
+    #[test]
+    fn reports_unknown_attributes_at_their_name() {
+        let diagnostic = validation_error(r#"<a href="x" hidden></a>"#);
+        assert_eq!(diagnostic.code.as_deref(), Some("T0301"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (12, 18));
+    }
+
+    #[test]
+    fn reports_attribute_types_at_their_name() {
+        let diagnostic = validation_error("<a href=1></a>");
+        assert_eq!(diagnostic.code.as_deref(), Some("T0302"));
+        assert_eq!((diagnostic.span.start, diagnostic.span.end), (3, 7));
+        let value = &diagnostic.labels[1];
+        assert_eq!((value.span.start, value.span.end), (8, 9));
+    }
+}
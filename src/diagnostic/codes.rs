@@ -0,0 +1,83 @@
+/// Every stable code a [`Diagnostic`](super::Diagnostic) can have, with the long-form explanation
+/// of it printed by the `explain` command.
+pub const ERROR_CODES: &[(&str, &str)] = &[
+    // Lexing
+    ("T0001", include_str!("explanations/T0001.md")),
+    ("T0002", include_str!("explanations/T0002.md")),
+    ("T0003", include_str!("explanations/T0003.md")),
+    ("T0004", include_str!("explanations/T0004.md")),
+    ("T0005", include_str!("explanations/T0005.md")),
+    ("T0006", include_str!("explanations/T0006.md")),
+    // Parsing
+    ("T0100", include_str!("explanations/T0100.md")),
+    ("T0101", include_str!("explanations/T0101.md")),
+    ("T0102", include_str!("explanations/T0102.md")),
+    ("T0103", include_str!("explanations/T0103.md")),
+    ("T0104", include_str!("explanations/T0104.md")),
+    ("T0105", include_str!("explanations/T0105.md")),
+    ("T0106", include_str!("explanations/T0106.md")),
+    ("T0107", include_str!("explanations/T0107.md")),
+    ("T0108", include_str!("explanations/T0108.md")),
+    ("T0109", include_str!("explanations/T0109.md")),
+    ("T0110", include_str!("explanations/T0110.md")),
+    ("T0111", include_str!("explanations/T0111.md")),
+    ("T0112", include_str!("explanations/T0112.md")),
+    ("T0113", include_str!("explanations/T0113.md")),
+    ("T0114", include_str!("explanations/T0114.md")),
+    ("T0115", include_str!("explanations/T0115.md")),
+    ("T0116", include_str!("explanations/T0116.md")),
+    // Loading includes, layouts and components
+    ("T0201", include_str!("explanations/T0201.md")),
+    ("T0202", include_str!("explanations/T0202.md")),
+    ("T0203", include_str!("explanations/T0203.md")),
+    ("T0204", include_str!("explanations/T0204.md")),
+    ("T0205", include_str!("explanations/T0205.md")),
+    ("T0206", include_str!("explanations/T0206.md")),
+    ("T0207", include_str!("explanations/T0207.md")),
+    ("T0208", include_str!("explanations/T0208.md")),
+    ("T0209", include_str!("explanations/T0209.md")),
+    ("T0210", include_str!("explanations/T0210.md")),
+    ("T0211", include_str!("explanations/T0211.md")),
+    ("T0212", include_str!("explanations/T0212.md")),
+    ("T0213", include_str!("explanations/T0213.md")),
+    ("T0214", include_str!("explanations/T0214.md")),
+    ("T0215", include_str!("explanations/T0215.md")),
+    // Validating against the schema
+    ("T0301", include_str!("explanations/T0301.md")),
+    ("T0302", include_str!("explanations/T0302.md")),
+    ("T0303", include_str!("explanations/T0303.md")),
+    ("T0304", include_str!("explanations/T0304.md")),
+    ("T0305", include_str!("explanations/T0305.md")),
+    ("T0306", include_str!("explanations/T0306.md")),
+    // Rendering
+    ("T0401", include_str!("explanations/T0401.md")),
+    ("T0402", include_str!("explanations/T0402.md")),
+    ("T0403", include_str!("explanations/T0403.md")),
+    ("T0404", include_str!("explanations/T0404.md")),
+    ("T0405", include_str!("explanations/T0405.md")),
+    ("T0406", include_str!("explanations/T0406.md")),
+    ("T0407", include_str!("explanations/T0407.md")),
+    ("T0408", include_str!("explanations/T0408.md")),
+    ("T0409", include_str!("explanations/T0409.md")),
+    ("T0410", include_str!("explanations/T0410.md")),
+    ("T0411", include_str!("explanations/T0411.md")),
+    ("T0412", include_str!("explanations/T0412.md")),
+    ("T0413", include_str!("explanations/T0413.md")),
+    ("T0414", include_str!("explanations/T0414.md")),
+    // Loading schema files
+    ("T0501", include_str!("explanations/T0501.md")),
+    ("T0502", include_str!("explanations/T0502.md")),
+    ("T0503", include_str!("explanations/T0503.md")),
+    // Lints
+    ("T0601", include_str!("explanations/T0601.md")),
+    ("T0602", include_str!("explanations/T0602.md")),
+    ("T0603", include_str!("explanations/T0603.md")),
+    ("T0604", include_str!("explanations/T0604.md")),
+];
+/// The explanation of `code`, which is matched case insensitively.
+pub fn explain(code: &str) -> Option<&'static str> {
+    ERROR_CODES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
@@ -0,0 +1,147 @@
+use ariadne::{Color, Label, Report, ReportKind};
+use serde::Serialize;
+
+use crate::{filestore::FileStore, spans::Span};
+
+mod codes;
+pub use codes::{ERROR_CODES, explain};
+
+/// How messages of the compiler are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageFormat {
+    /// Reports with the source code they point at, for reading in a terminal.
+    #[default]
+    Human,
+    /// One json object per line, see [`Diagnostic::to_json`].
+    Json,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Advice,
+}
+impl Severity {
+    fn report_kind(self) -> ReportKind<'static> {
+        match self {
+            Self::Error => ReportKind::Error,
+            Self::Warning => ReportKind::Warning,
+            Self::Advice => ReportKind::Advice,
+        }
+    }
+}
+#[derive(Debug, Clone)]
+pub struct DiagnosticLabel {
+    pub span: Span,
+    pub message: String,
+    /// Whether this points at the problem itself, other labels give context to it.
+    pub primary: bool,
+}
+/// A message of the compiler, see [`crate::lang_errors::MsgBuilder`] for building one.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The stable code of the message, it stays the same when its wording changes.
+    /// Its long-form explanation is in [`ERROR_CODES`].
+    pub code: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+    /// Where the message is reported at.
+    pub span: Span,
+    pub labels: Vec<DiagnosticLabel>,
+    pub help: Vec<String>,
+    pub notes: Vec<String>,
+}
+/// A [`Span`] resolved against the files it points into, lines and columns count characters from 1.
+///
+/// Like the offsets, `end_line` and `end_column` point right after the last character of the span.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// The path of the file, or its id for files that were not loaded from a path.
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+impl Location {
+    pub fn resolve(span: Span, files: &FileStore) -> Option<Self> {
+        let (line, column) = files.line_col(span.file_id, span.start)?;
+        let (end_line, end_column) = files.line_col(span.file_id, span.end)?;
+        Some(Self {
+            file: files
+                .name(span.file_id)
+                .map_or_else(|| span.file_id.to_string(), str::to_owned),
+            start: span.start,
+            end: span.end,
+            line,
+            column,
+            end_line,
+            end_column,
+        })
+    }
+}
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl ToString, span: Span) -> Self {
+        Self {
+            code: None,
+            severity,
+            message: message.to_string(),
+            span,
+            labels: vec![],
+            help: vec![],
+            notes: vec![],
+        }
+    }
+    /// The report printed for humans, with the source code it points at.
+    pub fn report(&self) -> Report<'static, Span> {
+        let mut builder =
+            Report::build(self.severity.report_kind(), self.span).with_message(&self.message);
+        if let Some(code) = &self.code {
+            builder = builder.with_code(code);
+        }
+        builder.with_helps(&self.help);
+        builder.with_notes(&self.notes);
+        builder
+            .with_labels(self.labels.iter().map(|label| {
+                let color = match (label.primary, self.severity) {
+                    (false, _) => Color::Fixed(210),
+                    (true, Severity::Error) => Color::Red,
+                    (true, Severity::Warning) => Color::Yellow,
+                    (true, Severity::Advice) => Color::Fixed(147),
+                };
+                Label::new(label.span)
+                    .with_message(&label.message)
+                    .with_color(color)
+            }))
+            .finish()
+    }
+    /// The message as json, with its spans resolved against `files`.
+    ///
+    /// Looks like `{"code", "severity", "message", "location", "labels": [{"message", "primary", "location"}], "help", "notes"}`,
+    /// locations are `null` when they point into a file missing from `files`.
+    pub fn to_json(&self, files: &FileStore) -> serde_json::Value {
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|label| {
+                serde_json::json!({
+                    "message": label.message,
+                    "primary": label.primary,
+                    "location": Location::resolve(label.span, files),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "code": self.code,
+            "severity": self.severity,
+            "message": self.message,
+            "location": Location::resolve(self.span, files),
+            "labels": labels,
+            "help": self.help,
+            "notes": self.notes,
+        })
+    }
+}
@@ -1,33 +1,76 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
 use ariadne::{Cache, Source};
 use slab::Slab;
 
 use crate::spans::FileID;
 
 #[derive(Debug, Clone, Default)]
-pub struct FileStore(Slab<Source>);
+pub struct FileStore {
+    files: Slab<Source>,
+    /// Display names of files that were loaded from a path.
+    names: HashMap<FileID, String>,
+}
 impl FileStore {
     pub fn new() -> Self {
-        Self(Slab::new())
+        Self::default()
     }
     pub fn add(&mut self, item: String) -> FileID {
-        self.0.insert(Source::from(item))
+        self.files.insert(Source::from(item))
+    }
+    /// Adds a file that is shown as `name` in reports.
+    pub fn add_named(&mut self, item: String, name: impl Into<String>) -> FileID {
+        let id = self.add(item);
+        self.names.insert(id, name.into());
+        id
     }
+    pub fn name(&self, id: FileID) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+    /// The line and column of the character at `offset` in the file `id`, both counted from 1.
+    ///
+    /// Offsets past the end of the file, like the one of the end of file token, resolve to its end.
+    pub fn line_col(&self, id: FileID, offset: usize) -> Option<(usize, usize)> {
+        let file = self.files.get(id)?;
+        let (_, line, column) = file.get_offset_line(offset.min(file.len()))?;
+        Some((line + 1, column + 1))
+    }
+}
+/// The chain of files from where `canonical` was first loaded back to it, when it is
+/// already in `stack`, the canonical paths of the files being loaded.
+pub(crate) fn cycle_chain(stack: &[PathBuf], canonical: &Path) -> Option<Vec<String>> {
+    let first = stack.iter().position(|loaded| loaded == canonical)?;
+    let mut chain: Vec<String> = stack[first..]
+        .iter()
+        .map(|loaded| loaded.display().to_string())
+        .collect();
+    chain.push(canonical.display().to_string());
+    Some(chain)
 }
 impl From<Slab<Source>> for FileStore {
     fn from(value: Slab<Source>) -> Self {
-        Self(value)
+        Self {
+            files: value,
+            names: HashMap::new(),
+        }
     }
 }
 impl Cache<FileID> for FileStore {
     type Storage = String;
     fn fetch(&mut self, id: &FileID) -> Result<&Source<Self::Storage>, impl std::fmt::Debug> {
-        let Some(file) = self.0.get(*id) else {
+        let Some(file) = self.files.get(*id) else {
             return Err(std::io::Error::other(format!("Invalid file id {id}")));
         };
 
         Ok(file)
     }
     fn display<'a>(&self, id: &'a FileID) -> Option<impl std::fmt::Display + 'a> {
-        Some(id)
+        match self.name(*id) {
+            Some(name) => Some(name.to_owned()),
+            None => Some(id.to_string()),
+        }
     }
 }
@@ -1,23 +1,58 @@
-use std::{
-    error::Error,
-    fmt::{Debug, Display},
-};
+use std::fmt::{Debug, Display};
 
+use crate::diagnostic::{Diagnostic, DiagnosticLabel, Severity};
+use crate::filestore::FileStore;
 use crate::spans::*;
-use ariadne::{Label, Report, ReportBuilder};
+use ariadne::Report;
 pub trait LangMessage
 where
     Self: SpanUtil + Debug,
 {
-    fn msg(&'_ self) -> Report<'_, Span>;
+    fn diagnostic(&self) -> Diagnostic;
+    /// The report printed for humans, see [`Diagnostic::report`].
+    fn msg(&'_ self) -> Report<'_, Span> {
+        self.diagnostic().report()
+    }
 }
 #[derive(Debug)]
 pub enum LangError {
     Compiler(Box<dyn LangMessage>),
     Io(std::io::Error),
     Serde(serde_json::Error),
+    /// A toml document that could not be deserialized, `line` and `column` count from 1.
+    Toml {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    /// A yaml document that could not be deserialized, `line` and `column` count from 1.
+    Yaml {
+        message: String,
+        line: usize,
+        column: usize,
+    },
     Other(Box<dyn std::error::Error>),
 }
+impl Display for LangError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compiler(msg) => write!(f, "{}", msg.diagnostic().message),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Serde(err) => write!(f, "{err}"),
+            Self::Toml {
+                message,
+                line,
+                column,
+            }
+            | Self::Yaml {
+                message,
+                line,
+                column,
+            } => write!(f, "{message} at line {line} column {column}"),
+            Self::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
 impl<T: LangMessage + 'static> From<T> for LangError {
     fn from(value: T) -> Self {
         let val = Box::new(value);
@@ -34,6 +69,16 @@ impl From<Box<dyn std::error::Error>> for LangError {
         Self::Other(value)
     }
 }
+impl From<&str> for LangError {
+    fn from(value: &str) -> Self {
+        Self::Other(value.into())
+    }
+}
+impl From<String> for LangError {
+    fn from(value: String) -> Self {
+        Self::Other(value.into())
+    }
+}
 impl From<serde_json::Error> for LangError {
     fn from(value: serde_json::Error) -> Self {
         if let Some(err) = value.io_error_kind() {
@@ -42,54 +87,157 @@ impl From<serde_json::Error> for LangError {
         Self::Serde(value)
     }
 }
-pub struct MsgBuilder<'a> {
-    inner: ReportBuilder<'a, Span>,
-    span: Span,
+impl From<serde_yaml::Error> for LangError {
+    fn from(value: serde_yaml::Error) -> Self {
+        let (line, column) = value
+            .location()
+            .map_or((0, 0), |location| (location.line(), location.column()));
+        let message = value.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {line} column {column}"))
+            .unwrap_or(&message);
+        Self::Yaml {
+            message: message.to_owned(),
+            line,
+            column,
+        }
+    }
 }
-impl<'a> MsgBuilder<'a> {
-    ///Instances [`MsgBuilder`] with an error message
-    pub fn build_err(msg: impl Display, span: Span) -> Self {
+impl LangError {
+    /// Converts the error of parsing the toml document `source`.
+    pub fn from_toml(value: toml::de::Error, source: &str) -> Self {
+        let start = value.span().map_or(0, |span| span.start.min(source.len()));
+        let before = &source.as_bytes()[..start];
+        let line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |at| at + 1);
+        Self::Toml {
+            message: value.message().to_owned(),
+            line: before.iter().filter(|byte| **byte == b'\n').count() + 1,
+            column: start - line_start + 1,
+        }
+    }
+    /// The error as json, see [`Diagnostic::to_json`].
+    ///
+    /// Deserialization errors only have the `line` and `column` of their location,
+    /// other errors without a span have a `null` location.
+    pub fn to_json(&self, files: &FileStore) -> serde_json::Value {
+        match self {
+            Self::Compiler(msg) => msg.diagnostic().to_json(files),
+            other => serde_json::json!({
+                "code": null,
+                "severity": Severity::Error,
+                "message": other.to_string(),
+                "location": other.location().map(|(line, column)| {
+                    serde_json::json!({ "line": line, "column": column })
+                }),
+                "labels": [],
+                "help": [],
+                "notes": [],
+            }),
+        }
+    }
+    /// The line and column a deserialization error happened at, both counted from 1.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Serde(err) if err.line() != 0 => Some((err.line(), err.column())),
+            Self::Toml { line, column, .. } | Self::Yaml { line, column, .. } if *line != 0 => {
+                Some((*line, *column))
+            }
+            _ => None,
+        }
+    }
+}
+pub struct MsgBuilder {
+    inner: Diagnostic,
+}
+impl MsgBuilder {
+    ///Instances [`MsgBuilder`] with a message of the given severity
+    pub fn build(severity: Severity, msg: impl Display, span: Span) -> Self {
         Self {
-            inner: Report::build(ariadne::ReportKind::Error, span).with_message(msg),
-            span,
+            inner: Diagnostic::new(severity, msg, span),
         }
     }
-    pub fn build_unspecified_err(msg: String, span: Span) -> Report<'a, Span> {
+    ///Instances [`MsgBuilder`] with an error message
+    pub fn build_err(msg: impl Display, span: Span) -> Self {
+        Self::build(Severity::Error, msg, span)
+    }
+    pub fn build_unspecified_err(msg: String, span: Span) -> Diagnostic {
         Self::build_err(msg, span)
             .with_err_label("On this expression".to_string())
             .finish()
     }
+    /// Adds the primary label, pointing at the span the message is reported at.
     pub fn with_err_label(mut self, msg: impl Display) -> Self {
-        self.inner = self.inner.with_label(
-            Label::new(self.span)
-                .with_message(msg)
-                .with_color(ariadne::Color::Red),
-        );
+        self.inner.labels.push(DiagnosticLabel {
+            span: self.inner.span,
+            message: msg.to_string(),
+            primary: true,
+        });
         self
     }
-    ///Returns the inner [`ReportBuilder`]
-    pub fn get_inner(self) -> ReportBuilder<'a, Span> {
-        self.inner
+    /// Adds a secondary label, pointing at something that gives context to the message.
+    pub fn with_label(mut self, span: Span, msg: impl Display) -> Self {
+        self.inner.labels.push(DiagnosticLabel {
+            span,
+            message: msg.to_string(),
+            primary: false,
+        });
+        self
     }
-    ///[`ReportBuilder::finish`]
-    pub fn finish(self) -> Report<'a, Span> {
-        self.inner.finish()
+    pub fn finish(self) -> Diagnostic {
+        self.inner
     }
-    ///[`ReportBuilder::with_code`]
+    ///Sets the stable code of the message, see [`Diagnostic::code`]
     pub fn with_code(mut self, code: impl Display) -> Self {
-        self.inner = self.inner.with_code(code);
-
+        self.inner.code = Some(code.to_string());
         self
     }
-    ///[`ReportBuilder::with_help`]
     pub fn with_help(mut self, help: impl Display) -> Self {
-        self.inner = self.inner.with_help(help);
+        self.inner.help.push(help.to_string());
         self
     }
-    ///[`ReportBuilder::with_note`]
     pub fn with_note(mut self, note: impl Display) -> Self {
-        self.inner = self.inner.with_note(note);
+        self.inner.notes.push(note.to_string());
         self
     }
 }
 pub type LangResult<T> = Result<T, LangError>;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value as JsonValue, json};
+
+    use super::LangError;
+    use crate::{Compiler, ast::schema::SchemaFormat, filestore::FileStore};
+
+    #[test]
+    fn deserialization_errors_have_a_json_location() {
+        for format in [SchemaFormat::Json, SchemaFormat::Toml, SchemaFormat::Yaml] {
+            let error = format
+                .parse::<JsonValue>("\n]")
+                .expect_err("The document is invalid");
+            let location = &error.to_json(&FileStore::new())["location"];
+            assert_eq!(location["line"], json!(2), "{format:?}");
+            assert!(location["column"].is_u64(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn other_errors_have_no_json_location() {
+        let error = LangError::from(std::io::Error::other("Could not read"));
+        assert_eq!(
+            error.to_json(&FileStore::new())["location"],
+            JsonValue::Null
+        );
+    }
+
+    #[test]
+    fn compiler_errors_display_their_message() {
+        let error = Compiler::make(FileStore::new(), true)
+            .parse("<p>{1 +}</p>")
+            .expect_err("The expression is not finished");
+        assert_eq!(error.to_string(), "Expected an expression, got 'RBrace'");
+    }
+}
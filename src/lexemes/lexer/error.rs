@@ -1,4 +1,5 @@
 use crate::{
+    diagnostic::Diagnostic,
     lang_errors::{LangMessage, MsgBuilder},
     spans::*,
 };
@@ -12,30 +13,36 @@ pub enum LexError {
     UnexpectedStreamEnd,
 }
 impl LangMessage for Spanned<LexError> {
-    fn msg(&'_ self) -> ariadne::Report<'_, Span> {
+    fn diagnostic(&self) -> Diagnostic {
         use LexError as Le;
         match self.item {
             Le::InvalidIdent => MsgBuilder::build_err("Invalid identifier", self.span)
+                .with_code("T0003")
                 .with_err_label("This contains special charaters.")
                 .with_note("Identifiers can only be made up of ascii charaters.")
                 .finish(),
             Le::InvalidNumber => MsgBuilder::build_err("Invalid number", self.span)
+                .with_code("T0004")
                 .with_err_label("This is not a valid number.")
                 .finish(),
             Le::UnexpectedStreamEnd => {
                 MsgBuilder::build_err("Unexpected end of character stream", self.span)
+                    .with_code("T0006")
                     .with_err_label("Expected more tokens here.")
                     .finish()
             }
             Le::UnexpectedChar(c) => {
                 MsgBuilder::build_err(format!("Unexpected char '{c}'"), self.span)
+                    .with_code("T0002")
                     .with_err_label("This should not be here.")
                     .finish()
             }
             Le::UnterminatedStr(c) => MsgBuilder::build_err("Unterminated string", self.span)
+                .with_code("T0001")
                 .with_err_label(format!("Missing '{c}'."))
                 .finish(),
             Le::InvalidEscape => MsgBuilder::build_err("Invalid escape sequence", self.span)
+                .with_code("T0005")
                 .with_err_label("This is not a valid escape sequence.".to_string())
                 .with_note(r#"The only valid escape sequences are:  \", \\, \', \n, \t, \0 ."#)
                 .finish(),
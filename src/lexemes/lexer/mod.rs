@@ -1,10 +1,8 @@
 use super::tokens::*;
 use crate::charvec::CharVec;
-use crate::lang_errors::{LangMessage, LangResult};
+use crate::lang_errors::LangResult;
 use crate::lexemes::*;
-use crate::spans::{FileID, IntoSpanned, Span, Spanned};
-use std::fmt::Write;
-use std::future;
+use crate::spans::{FileID, IntoSpanned, Span};
 use std::str::Chars;
 mod error;
 
@@ -14,15 +12,20 @@ pub type Result<T = Token> = LangResult<T>;
 pub struct Lexer<'a> {
     file_id: FileID,
     chars: Chars<'a>,
-    source: String,
     pub(crate) index: usize,
     lex_whitespace: bool,
     lex_comments: bool,
+    /// Whether the last significant token can end an operand, a `-` after it is not the sign of a number.
+    after_operand: bool,
 }
 impl<'a> Lexer<'a> {
     pub(crate) fn peek_char(&self) -> Option<char> {
         self.chars.clone().next()
     }
+    /// The source that was not lexed yet.
+    pub(crate) fn rest(&self) -> &'a str {
+        self.chars.as_str()
+    }
     fn skip(&mut self,n:usize) {
         for _ in 0..n {
             self.advance();
@@ -40,7 +43,7 @@ impl<'a> Lexer<'a> {
                 return false;
             }
         }
-        return true;
+        true
     }
     fn make_error<T>(&self, err: LexError, start: usize, stop: usize) -> Result<T> {
         let inner = err.to_spanned(self.new_span(start, stop));
@@ -59,7 +62,7 @@ impl<'a> Lexer<'a> {
         if advanced != expected {
             return self.make_error(LexError::UnexpectedChar(advanced), start, start + 1);
         }
-        return Ok(advanced);
+        Ok(advanced)
     }
     pub(crate) fn advance(&mut self) -> Option<char> {
         self.index += 1;
@@ -78,9 +81,6 @@ impl<'a> Lexer<'a> {
         let start = self.index;
 
         let mut current = self.peek_char();
-        if current.is_some_and(|x| x == '-') {
-            current = self.advance();
-        }
         while let Some(value) = current {
             if !value.is_numeric() && value != '.' && value != '_' {
                 break;
@@ -112,8 +112,9 @@ impl<'a> Lexer<'a> {
             self.new_span(start - 1, self.index),
         ))
     }
-    fn lex_identifier(&mut self) -> Result {
+    fn lex_identifier(&mut self, first: char) -> Result {
         let start = self.index - 1;
+        let rest = self.chars.as_str();
         let mut current = self.peek_char();
         while let Some(value) = current {
             if value.is_alphanumeric() || value == '_' || value == '-' || value == ':' {
@@ -124,10 +125,9 @@ impl<'a> Lexer<'a> {
             break;
         }
         let stop = self.index;
-        let Some(span) = self.source.get(start..stop) else {
-            return self.make_error(LexError::InvalidIdent, start, stop);
-        };
-        let kind = tokens::map_keyword(span).unwrap_or(TokenType::Word);
+        let lexed = rest.len() - self.chars.as_str().len();
+        let text = format!("{first}{}", &rest[..lexed]);
+        let kind = tokens::map_keyword(&text).unwrap_or(TokenType::Word);
         Ok(Token::new(kind, self.new_span(start, stop)))
     }
     fn lex_string(&mut self, quote: char) -> Result {
@@ -146,8 +146,6 @@ impl<'a> Lexer<'a> {
                         break;
                     }
                     buffer.push(q);
-                    let bytecount = q.len_utf8();
-                    self.index += bytecount.saturating_sub(1);
                 }
                 (true, ch) => {
                     let escape_map = match ch {
@@ -200,7 +198,7 @@ impl<'a> Lexer<'a> {
             return self.lex_number();
         }
         if expected.is_alphanumeric() || expected == '_' {
-            return self.lex_identifier();
+            return self.lex_identifier(expected);
         }
         self.make_error(LexError::UnexpectedChar(expected), start - 1, start)
     }
@@ -249,9 +247,9 @@ impl<'a> Lexer<'a> {
             }
         }
        if self.lex_comments {
-            return Ok(Token::new(TokenType::Comment, self.new_span(start, end)));
+            Ok(Token::new(TokenType::Comment, self.new_span(start, end)))
         } else {
-            return self.next();
+            self.next()
         }
     }
     fn lex_html_comment(&mut self) -> Result {
@@ -280,9 +278,9 @@ impl<'a> Lexer<'a> {
             }
         }
         if self.lex_comments {
-            return Ok(Token::new(TokenType::Comment, self.new_span(start, end)));
+            Ok(Token::new(TokenType::Comment, self.new_span(start, end)))
         } else {
-            return self.next();
+            self.next()
         }
     }   
    
@@ -322,7 +320,7 @@ impl<'a> Lexer<'a> {
         self.advance();
         let mut span = range;
         span.end = self.index;
-        return Ok(Token::new(TokenType::End, span));
+        Ok(Token::new(TokenType::End, span))
     }
      fn lex_lesser_token(&mut self, range: Span) -> Result {
         let Some(peeked) = self.peek_char() else {
@@ -368,15 +366,16 @@ impl<'a> Lexer<'a> {
             '!' => just(T::Bang),
 
             '*' => just(T::Star),
+            '+' => just(T::Plus),
             '-' => {
 
                 let Some(peeked) = self.peek_char() else {
                     return Ok(Token::new(TokenType::Minus, range));
                 };
-                if peeked.is_alphanumeric() {
+                if peeked.is_ascii_digit() && !self.after_operand {
                     return self.lex_number();
                 }
-                return Ok(Token::new(TokenType::Minus, range));
+                Ok(Token::new(TokenType::Minus, range))
             }
             '>' => just(T::Greater),
             '/' => self.multi_char_token('>', T::Slash, T::RCloser, start),
@@ -389,27 +388,36 @@ impl<'a> Lexer<'a> {
     pub fn peek_next(&mut self) -> Result {
         let old_chars = self.chars.clone();
         let old_index = self.index;
-        self.next()?;
-        let token = self.next()?;
+        let old_after_operand = self.after_operand;
+        let token = self.next().and_then(|_| self.next());
         self.chars = old_chars;
         self.index = old_index;
-        Ok(token)
+        self.after_operand = old_after_operand;
+        token
     }
+    /// Lexes the current token without consuming it, nothing is consumed when lexing fails either.
     pub fn peek(&mut self) -> Result {
         let old_chars = self.chars.clone();
         let old_index = self.index;
-        let token = self.next()?;
+        let old_after_operand = self.after_operand;
+        let token = self.next();
         self.chars = old_chars;
         self.index = old_index;
-        Ok(token)
+        self.after_operand = old_after_operand;
+        token
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result {
         let start = self.index;
         let Some(last) = self.advance() else {
             return self.make_eof_token();
         };
 
-        self.token_from_char(last, start)   
+        let token = self.token_from_char(last, start)?;
+        if token.is_significant() {
+            self.after_operand = token.kind.ends_operand();
+        }
+        Ok(token)
     }
     pub fn toggle_whitespace(&mut self,value:bool) {
         self.lex_whitespace = value;
@@ -417,6 +425,10 @@ impl<'a> Lexer<'a> {
     pub fn toggle_comments(&mut self,value:bool) {
         self.lex_comments = value;
     }
+    /// Whether whitespace tokens are currently being produced.
+    pub fn lexes_unsignificant(&self) -> bool {
+        self.lex_whitespace
+    }
     pub fn toggle_unsignificant(&mut self,value:bool) {
         self.lex_whitespace = value;
         self.lex_comments = value;
@@ -425,10 +437,10 @@ impl<'a> Lexer<'a> {
         Self {
             file_id,
             chars: src.chars(),
-            source: String::from(src),
             index: 0,
             lex_whitespace: true,
-            lex_comments:true
+            lex_comments:true,
+            after_operand: false,
         }
     }
 }
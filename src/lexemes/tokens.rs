@@ -9,6 +9,7 @@ pub enum TokenType {
     Float,
     Int,
     Word,
+    Plus,
     Minus,
     Star,
     Slash,
@@ -45,11 +46,26 @@ impl TokenType {
     pub fn to_token(self, span: Span) -> Token {
         Token::new(self, span)
     }
+    /// Whether a token of this kind can end an operand, so a `-` right after it is a subtraction.
+    pub fn ends_operand(&self) -> bool {
+        matches!(
+            self,
+            Self::Str(_)
+                | Self::Float
+                | Self::Int
+                | Self::Word
+                | Self::True
+                | Self::False
+                | Self::Null
+                | Self::RParen
+                | Self::RBracket
+        )
+    }
 }
 
 impl AsRef<TokenType> for TokenType {
     fn as_ref(&self) -> &TokenType {
-        &self
+        self
     }
 }
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -96,7 +112,7 @@ pub trait TokenEq {
     }
     /// Inverse of [`Self::is`].
     fn isnt(&self, kind: impl AsRef<TokenType>) -> bool {
-        return !self.is(kind);
+        !self.is(kind)
     }
     /// Inverse of [`Self::is_significant`].
     fn isnt_significant(&self) -> bool {
@@ -104,15 +120,15 @@ pub trait TokenEq {
     }
     /// Inverse of [`Self::is_any`].
     fn isnt_any(&self, matches: impl AsRef<[TokenType]>) -> bool {
-        return !self.is_any(matches);
+        !self.is_any(matches)
     }
     fn is_delimiter(&self) -> bool {
-        return self.is(TokenType::Lesser)
+        self.is(TokenType::Lesser)
             || self.is(TokenType::LCloser)
-            || self.is(TokenType::End);
+            || self.is(TokenType::End)
     }
     fn exists(&self) -> bool {
-        return self.isnt(&TokenType::Eof);
+        self.isnt(&TokenType::Eof)
     }
 }
 impl Token {
@@ -1,24 +1,50 @@
 pub mod ast;
 mod charvec;
+pub mod diagnostic;
 mod filestore;
 pub mod lang_errors;
 pub mod lexemes;
+pub mod loader;
+pub mod render;
 pub mod spans;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
 use filestore::FileStore;
 use lexemes::lexer::Lexer;
 use lexemes::tokens::*;
 
+use crate::ast::component::PropDecl;
+use crate::ast::lint::{LintLevel, lint};
 use crate::ast::nodes::Node;
 use crate::ast::parser::Parser;
-use crate::ast::schema::{self, ElementSchema, SchemaLoadError};
-use crate::lang_errors::{LangMessage, LangResult};
-use crate::spans::Spanned;
+use crate::ast::schema::{ElementSchema, SchemaLoader};
+use crate::ast::validate::validate;
+use crate::diagnostic::MessageFormat;
+use crate::lang_errors::{LangError, LangMessage, LangResult};
+use crate::loader::Loader;
+use crate::render::filters::FilterRegistry;
+use crate::render::{Children, ComponentRegistry, Context, HtmlRenderer, Props, RenderCtx, Sink};
+use crate::spans::{FileID, Spanned};
 
 pub struct Compiler {
     pub file_store: FileStore,
+    pub schema: ElementSchema,
+    /// Components usable in every template, see [`Compiler::define_component`]
+    /// and [`Compiler::register_component`].
+    pub components: ComponentRegistry,
+    /// Filters usable through pipes, the built-in ones and those added with [`Compiler::register_filter`].
+    pub filters: FilterRegistry,
     silent: bool,
+    message_format: MessageFormat,
+}
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Compiler {
     pub fn take_filestore(self) -> FileStore {
@@ -28,27 +54,106 @@ impl Compiler {
         &self.file_store
     }
     pub fn make(file_store: FileStore, silent: bool) -> Self {
-        Self { file_store, silent }
+        Self {
+            file_store,
+            schema: ElementSchema::html5(),
+            components: ComponentRegistry::new(),
+            filters: FilterRegistry::new(),
+            silent,
+            message_format: MessageFormat::default(),
+        }
     }
     pub fn new() -> Self {
         Self {
             silent: false,
-
+            message_format: MessageFormat::default(),
+            schema: ElementSchema::html5(),
+            components: ComponentRegistry::new(),
+            filters: FilterRegistry::new(),
             file_store: FileStore::new(),
         }
     }
+    pub fn with_schema(self, schema: ElementSchema) -> Self {
+        Self { schema, ..self }
+    }
+    /// Sets how errors are printed to stderr, [`MessageFormat::Human`] by default.
+    pub fn with_message_format(self, message_format: MessageFormat) -> Self {
+        Self {
+            message_format,
+            ..self
+        }
+    }
+    /// Merges the rules of the schema file at `path` on top of the current ones,
+    /// which are the [`ElementSchema::html5`] preset unless replaced with [`Compiler::with_schema`].
+    ///
+    /// The schema file can extend other schema files, they are all added to the file store.
+    pub fn with_schema_file(mut self, path: impl AsRef<Path>) -> LangResult<Self> {
+        self.schema
+            .merge_file(path, &mut self.file_store)
+            .inspect_err(|err| self.report(err))?;
+        Ok(self)
+    }
+    /// Like [`Compiler::with_schema_file`], but unknown fields in the rules of an element
+    /// are an error instead of being ignored.
+    pub fn with_strict_schema_file(mut self, path: impl AsRef<Path>) -> LangResult<Self> {
+        SchemaLoader::new(&mut self.file_store)
+            .with_strict(true)
+            .merge_file(&mut self.schema, path)
+            .inspect_err(|err| self.report(err))?;
+        Ok(self)
+    }
+    /// Defines a component usable as `<name>` in every template parsed afterwards.
+    ///
+    /// Inside of `body` the properties are variables and `<children/>` renders the
+    /// children the component was given. Files `body` includes are resolved from the current directory.
+    pub fn define_component(
+        &mut self,
+        name: impl Into<String>,
+        props: Vec<PropDecl>,
+        body: &str,
+    ) -> LangResult<()> {
+        let name = name.into();
+        let file_id = self
+            .file_store
+            .add_named(body.to_owned(), format!("<component {name}>"));
+        let def = Parser::make(body, file_id, self.schema.clone())
+            .parse()
+            .and_then(|nodes| {
+                Loader::new(&mut self.file_store, &self.schema)
+                    .with_components(self.components.templates().clone())
+                    .define(name, props, nodes, Path::new(""))
+                    .cloned()
+            })
+            .inspect_err(|err| self.report(err))?;
+        self.components.add_template(def);
+        Ok(())
+    }
+    /// Registers a component implemented in Rust, it is called every time an element named `name` is rendered.
+    ///
+    /// Errors it returns that have no location are reported at the start tag of the element.
+    pub fn register_component<F>(&mut self, name: impl Into<String>, component: F)
+    where
+        F: Fn(&Props, Children, &mut RenderCtx) -> LangResult<()> + Send + Sync + 'static,
+    {
+        self.components.register(name, component);
+    }
+    /// Registers a filter usable like `{value | name(args)}`, called with a number of arguments in `args`.
+    ///
+    /// Errors it returns that have no location are reported at the name of the filter.
+    pub fn register_filter(
+        &mut self,
+        name: impl Into<String>,
+        args: RangeInclusive<usize>,
+        filter: impl Fn(&JsonValue, &[JsonValue]) -> LangResult<JsonValue> + Send + Sync + 'static,
+    ) {
+        self.filters.register(name, args, filter);
+    }
     pub fn lex(&mut self, input: &str) -> LangResult<Vec<Token>> {
         let file_id = self.file_store.add(input.to_owned());
         let mut lexer = Lexer::new(input, file_id);
         let mut buf = vec![];
         loop {
-            let tok = lexer.next().inspect_err(|err| {
-                if !self.silent {
-                    err.msg()
-                        .eprint(self.file_store.clone())
-                        .expect("Could not print error.");
-                }
-            })?;
+            let tok = lexer.next().inspect_err(|err| self.report(err))?;
             if tok.is(&TokenType::Eof) {
                 break;
             }
@@ -56,24 +161,182 @@ impl Compiler {
         }
         Ok(buf)
     }
-    pub fn parse(
-        &mut self,
-        input: &str,
-        schema_path: impl AsRef<Path>,
-    ) -> LangResult<Vec<Spanned<Node>>> {
+    /// Parses `input`, files it includes or extends are resolved from the current directory.
+    ///
+    /// Then runs the lints over it, see [`Compiler::check_lints`].
+    pub fn parse(&mut self, input: &str) -> LangResult<Vec<Spanned<Node>>> {
         let file_id = self.file_store.add(input.to_owned());
 
-        Parser::new(input, file_id)
-            .with_schema_file(schema_path)
-            .inspect_err(|err| {
-                if !self.silent {
-                    err.msg()
-                        .eprint(self.file_store.clone())
-                        .expect("Could not print error.");
-                }
+        let nodes = Parser::make(input, file_id, self.schema.clone())
+            .parse()
+            .and_then(|nodes| {
+                Loader::new(&mut self.file_store, &self.schema)
+                    .with_components(self.components.templates().clone())
+                    .resolve(nodes, Path::new(""))
+            })
+            .and_then(|mut nodes| {
+                validate(&mut nodes, &self.schema)?;
+                Ok(nodes)
             })
+            .inspect_err(|err| self.report(err))?;
+        self.check_lints(&nodes)?;
+        Ok(nodes)
+    }
+    /// Parses `input` without stopping at the first error, every error found is reported.
+    ///
+    /// Returns the nodes that could be parsed, with a [`Node::Error`] in place of each part
+    /// that could not, along with the errors. Includes, layouts and components are not resolved.
+    pub fn parse_recovering(&mut self, input: &str) -> (Vec<Spanned<Node>>, Vec<LangError>) {
+        let file_id = self.file_store.add(input.to_owned());
+        self.recover_source(input, file_id)
+    }
+    /// Like [`Compiler::parse_recovering`], for the template at `path`.
+    pub fn parse_file_recovering(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> (Vec<Spanned<Node>>, Vec<LangError>) {
+        let path = path.as_ref();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                let err = LangError::from(err);
+                self.report(&err);
+                return (vec![], vec![err]);
+            }
+        };
+        let file_id = self
+            .file_store
+            .add_named(text.clone(), path.display().to_string());
+        self.recover_source(&text, file_id)
+    }
+    fn recover_source(
+        &mut self,
+        input: &str,
+        file_id: FileID,
+    ) -> (Vec<Spanned<Node>>, Vec<LangError>) {
+        let (nodes, errors) = Parser::make(input, file_id, self.schema.clone()).parse_recovering();
+        for err in &errors {
+            self.report(err);
+        }
+        (nodes, errors)
+    }
+    /// Parses the template at `path`, files it includes or extends are resolved from its directory.
+    ///
+    /// Then runs the lints over it, see [`Compiler::check_lints`].
+    pub fn parse_file(&mut self, path: impl AsRef<Path>) -> LangResult<Vec<Spanned<Node>>> {
+        let nodes = Loader::new(&mut self.file_store, &self.schema)
+            .with_components(self.components.templates().clone())
+            .load_file(path)
+            .and_then(|mut nodes| {
+                validate(&mut nodes, &self.schema)?;
+                Ok(nodes)
+            })
+            .inspect_err(|err| self.report(err))?;
+        self.check_lints(&nodes)?;
+        Ok(nodes)
+    }
+    /// Runs the lints of the schema over `nodes` and reports what they find.
+    ///
+    /// Fails with the first thing found by a lint set to deny, once everything was reported.
+    pub fn check_lints(&self, nodes: &[Spanned<Node>]) -> LangResult<()> {
+        let reports = lint(nodes, &self.schema);
+        for report in &reports {
+            self.report(&report.clone().into());
+        }
+        match reports
+            .into_iter()
+            .find(|report| report.level == LintLevel::Deny)
+        {
+            Some(denied) => Err(denied.into()),
+            None => Ok(()),
+        }
+    }
+    /// Parses `input` and renders it to an html string.
+    pub fn render_html(&mut self, input: &str) -> LangResult<String> {
+        self.render_html_to(input, String::new(), Context::new())
+    }
+    /// Parses `template` and renders it with the fields of `data` as variables.
+    pub fn render(&mut self, template: &str, data: &impl Serialize) -> LangResult<String> {
+        self.render_to(template, data, String::new())
+    }
+    /// Parses `template` and streams it into `sink`, rendered with the fields of `data` as variables.
+    pub fn render_to<W: Sink>(
+        &mut self,
+        template: &str,
+        data: &impl Serialize,
+        sink: W,
+    ) -> LangResult<W> {
+        let ctx = Context::from_serialize(data).inspect_err(|err| self.report(err))?;
+        self.render_html_to(template, sink, ctx)
+    }
+    /// Parses the template at `path` and renders it with the fields of `data` as variables.
+    pub fn render_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        data: &impl Serialize,
+    ) -> LangResult<String> {
+        self.render_file_to(path, data, String::new())
+    }
+    /// Parses the template at `path` and streams it into `sink`, rendered with the fields of `data` as variables.
+    pub fn render_file_to<W: Sink>(
+        &mut self,
+        path: impl AsRef<Path>,
+        data: &impl Serialize,
+        sink: W,
+    ) -> LangResult<W> {
+        let ctx = Context::from_serialize(data).inspect_err(|err| self.report(err))?;
+        let nodes = self.parse_file(path)?;
+        self.render_nodes_to(&nodes, sink, ctx)
+    }
+    /// Parses `input` and streams the html rendered against `ctx` into `sink`.
+    pub fn render_html_to<W: Sink>(&mut self, input: &str, sink: W, ctx: Context) -> LangResult<W> {
+        let nodes = self.parse(input)?;
+        self.render_nodes_to(&nodes, sink, ctx)
+    }
+    /// Streams the html of already parsed `nodes` rendered against `ctx` into `sink`.
+    pub fn render_nodes_to<W: Sink>(
+        &mut self,
+        nodes: &[Spanned<Node>],
+        sink: W,
+        ctx: Context,
+    ) -> LangResult<W> {
+        HtmlRenderer::from_sink(sink)
+            .with_context(ctx)
+            .with_components(self.components.natives().clone())
+            .with_filters(self.filters.clone())
+            .with_schema(self.schema.clone())
+            .render(nodes)
+            .inspect_err(|err| self.report(err))
     }
     pub fn print_langerr(&self, err: &dyn LangMessage) -> std::io::Result<()> {
         err.msg().eprint(self.file_store.clone())
     }
+    /// Prints `err` to stderr in the message format of the compiler, unless it is silent.
+    pub fn report(&self, err: &LangError) {
+        if self.silent {
+            return;
+        }
+        if self.message_format == MessageFormat::Json {
+            eprintln!("{}", err.to_json(&self.file_store));
+            return;
+        }
+        match err {
+            LangError::Compiler(msg) => self
+                .print_langerr(msg.as_ref())
+                .expect("Could not print error."),
+            other => eprintln!("Error: {other}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compiler;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn compiler_is_send_and_sync() {
+        assert_send_sync::<Compiler>();
+    }
 }
@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use crate::{
+    ast::{
+        component::{ComponentDef, PropDecl, PropType},
+        expr::Expr,
+        nodes::*,
+    },
+    render::type_name,
+    spans::{IntoSpanned, Spanned},
+};
+
+use super::{LoadError, Loader, Result, err};
+
+pub const COMPONENT_TAG: &str = "component";
+/// Placeholder for the children a component was given.
+pub const CHILDREN_TAG: &str = "children";
+/// The property of `<component>` listing the properties it accepts.
+pub const PROPS_PROP: &str = "props";
+
+impl Loader<'_> {
+    /// Whether elements named `name` use a component, including the ones being defined.
+    pub(super) fn is_component(&self, name: &str) -> bool {
+        self.components.contains_key(name) || self.expanding.iter().any(|def| def == name)
+    }
+    /// Registers the component defined by a `<component>` element.
+    pub(super) fn define_element(&mut self, element: Element, dir: &Path) -> Result<()> {
+        let name = match element.props.get("name").map(|prop| &prop.value) {
+            Some(Spanned {
+                item: Value::String(name),
+                ..
+            }) => name.clone(),
+            _ => {
+                return err(LoadError::MissingComponentName.to_spanned(element.start_tag_span));
+            }
+        };
+        let props = match element.props.get(PROPS_PROP).map(|prop| &prop.value) {
+            None => vec![],
+            Some(Spanned {
+                item: Value::String(decls),
+                span,
+            }) => PropDecl::parse_list(decls)
+                .map_err(|reason| LoadError::InvalidPropList(reason).to_spanned(*span))?,
+            Some(other) => {
+                let reason = "The property list has to be a string literal".to_owned();
+                return err(LoadError::InvalidPropList(reason).to_spanned(other.span));
+            }
+        };
+        self.define(name, props, element.children, dir)?;
+        Ok(())
+    }
+    /// Registers a component, components used in its body have to be registered already.
+    ///
+    /// Relative paths in the body are resolved from `dir`.
+    pub fn define(
+        &mut self,
+        name: String,
+        props: Vec<PropDecl>,
+        body: Vec<Spanned<Node>>,
+        dir: &Path,
+    ) -> Result<&ComponentDef> {
+        self.expanding.push(name.clone());
+        let body = self.expand(body, dir);
+        self.expanding.pop();
+        let def = ComponentDef {
+            name: name.clone(),
+            props,
+            body: body?,
+        };
+        Ok(self.components.entry(name).insert_entry(def).into_mut())
+    }
+    /// Replaces the use of a component with its body, checking the properties it was given.
+    pub(super) fn call(&mut self, element: Element, dir: &Path) -> Result<ComponentCall> {
+        let name = element.name;
+        if self.expanding.contains(&name) {
+            return err(LoadError::RecursiveComponent(name).to_spanned(element.start_tag_span));
+        }
+        let def = &self.components[&name];
+        let mut given = element.props;
+        let mut props = Vec::with_capacity(def.props.len());
+        for decl in &def.props {
+            let Some(Prop { value, .. }) = given.shift_remove(&decl.name) else {
+                if decl.required {
+                    return err(LoadError::MissingProp {
+                        component: name,
+                        prop: decl.name.clone(),
+                    }
+                    .to_spanned(element.start_tag_span));
+                }
+                let value = Expr::Literal(Value::Null).to_spanned(element.start_tag_span);
+                props.push(BoundProp {
+                    name: decl.name.clone(),
+                    ty: decl.ty,
+                    value,
+                });
+                continue;
+            };
+            props.push(BoundProp {
+                name: decl.name.clone(),
+                ty: decl.ty,
+                value: prop_value(value, decl.ty)?,
+            });
+        }
+        if let Some((prop, Prop { name_span, .. })) = given.into_iter().next() {
+            return err(LoadError::UnknownProp {
+                component: name,
+                prop,
+            }
+            .to_spanned(name_span));
+        }
+        let body = def.body.clone();
+        let children = self.expand(element.children, dir)?;
+        Ok(ComponentCall {
+            name,
+            props,
+            body: fill_children(body, &children),
+            start_tag_span: element.start_tag_span,
+        })
+    }
+}
+/// Turns a property given to a component into an expression, type checking it when it is a literal.
+fn prop_value(value: Spanned<Value>, ty: PropType) -> Result<Spanned<Expr>> {
+    if let Value::Expr(expr) = value.item {
+        return Ok(*expr);
+    }
+    let literal = value.to_json();
+    if !ty.accepts(&literal) {
+        return err(LoadError::PropType {
+            expected: ty,
+            found: type_name(&literal),
+        }
+        .to_spanned(value.span));
+    }
+    Ok(Expr::Literal(value.item).to_spanned(value.span))
+}
+/// Replaces every `<children/>` in `nodes` with a slot holding `children`.
+fn fill_children(nodes: Vec<Spanned<Node>>, children: &[Spanned<Node>]) -> Vec<Spanned<Node>> {
+    let mut filled = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        if let Node::Element(element) = &node.item
+            && element.name == CHILDREN_TAG
+        {
+            filled.push(Node::Slot(children.to_vec()).to_spanned(node.span));
+            continue;
+        }
+        for nested in node.item.children_mut() {
+            *nested = fill_children(std::mem::take(nested), children);
+        }
+        filled.push(node);
+    }
+    filled
+}
@@ -0,0 +1,164 @@
+use crate::{
+    ast::component::PropType,
+    diagnostic::Diagnostic,
+    lang_errors::{LangMessage, MsgBuilder},
+    spans::*,
+};
+#[derive(Debug, Clone)]
+pub enum LoadError {
+    /// The element has no `src` property or it is not a string.
+    MissingSource(String),
+    NotFound {
+        path: String,
+        reason: String,
+    },
+    /// The file is already being loaded further up, holds the chain of files that lead back to it.
+    Cycle(Vec<String>),
+    MissingBlockName,
+    /// Anything but blocks in a template that extends another one.
+    ContentOutsideBlock,
+    DuplicateBlock {
+        name: String,
+        first: Span,
+    },
+    /// A block override for a block the layout does not have.
+    UnknownBlock(String),
+    /// A `<parent/>` that is not inside of a block override.
+    MisplacedParent,
+    MissingComponentName,
+    /// The `props` of a `<component>` could not be parsed, holds the reason.
+    InvalidPropList(String),
+    UnknownProp {
+        component: String,
+        prop: String,
+    },
+    MissingProp {
+        component: String,
+        prop: String,
+    },
+    PropType {
+        expected: PropType,
+        found: &'static str,
+    },
+    /// A component used inside of its own body.
+    RecursiveComponent(String),
+    /// A `<children/>` that is not inside of a component.
+    MisplacedChildren,
+}
+impl LangMessage for Spanned<LoadError> {
+    fn diagnostic(&self) -> Diagnostic {
+        use LoadError as Le;
+        match &self.item {
+            Le::MissingSource(name) => {
+                MsgBuilder::build_err(format!("'{name}' needs a 'src' to load"), self.span)
+                    .with_code("T0201")
+                    .with_err_label("Add a 'src' property to this tag.")
+                    .with_note("'src' has to be a string literal, expressions are not allowed.")
+                    .finish()
+            }
+            Le::NotFound { path, reason } => {
+                MsgBuilder::build_err(format!("Could not load '{path}'"), self.span)
+                    .with_code("T0202")
+                    .with_err_label(reason)
+                    .with_note("Paths are relative to the directory of the template loading them.")
+                    .finish()
+            }
+            Le::Cycle(chain) => {
+                let first = chain.first().map(String::as_str).unwrap_or_default();
+                MsgBuilder::build_err(format!("'{first}' ends up loading itself"), self.span)
+                    .with_code("T0203")
+                    .with_err_label("This starts the cycle.")
+                    .with_note(format!("The chain is {}.", chain.join(" -> ")))
+                    .finish()
+            }
+            Le::MissingBlockName => MsgBuilder::build_err("Block without a name", self.span)
+                .with_code("T0204")
+                .with_err_label("Add a 'name' string property to this block.")
+                .finish(),
+            Le::ContentOutsideBlock => {
+                MsgBuilder::build_err("Content outside of a block", self.span)
+                    .with_code("T0205")
+                    .with_err_label("This is not inside of a 'block'.")
+                    .with_note(
+                        "A template using 'extends' can only contain the blocks it overrides.",
+                    )
+                    .finish()
+            }
+            Le::DuplicateBlock { name, first } => {
+                MsgBuilder::build_err(format!("Block '{name}' is overridden twice"), self.span)
+                    .with_code("T0206")
+                    .with_err_label("This is the second override.")
+                    .with_label(*first, "The first one is here.")
+                    .finish()
+            }
+            Le::UnknownBlock(name) => {
+                MsgBuilder::build_err(format!("The layout has no block '{name}'"), self.span)
+                    .with_code("T0207")
+                    .with_err_label("This does not override anything.")
+                    .with_help("Check the spelling of the name against the layout's blocks.")
+                    .finish()
+            }
+            Le::MisplacedParent => {
+                MsgBuilder::build_err("'parent' outside of a block override", self.span)
+                    .with_code("T0208")
+                    .with_err_label("There is no overridden content to render here.")
+                    .with_note("'<parent/>' renders the layout's content of the block it is in.")
+                    .finish()
+            }
+            Le::MissingComponentName => {
+                MsgBuilder::build_err("Component without a name", self.span)
+                    .with_code("T0209")
+                    .with_err_label("Add a 'name' string property to this component.")
+                    .finish()
+            }
+            Le::InvalidPropList(reason) => {
+                MsgBuilder::build_err("Invalid property list", self.span)
+                    .with_code("T0210")
+                    .with_err_label(reason)
+                    .with_help("Properties are listed like \"title: string, count?: int, body\".")
+                    .with_note("The types are any, string, int, float and bool.")
+                    .finish()
+            }
+            Le::UnknownProp { component, prop } => MsgBuilder::build_err(
+                format!("Component '{component}' has no property '{prop}'"),
+                self.span,
+            )
+            .with_code("T0211")
+            .with_err_label("This property is not declared by the component.")
+            .finish(),
+            Le::MissingProp { component, prop } => MsgBuilder::build_err(
+                format!("Missing property '{prop}' of component '{component}'"),
+                self.span,
+            )
+            .with_code("T0212")
+            .with_err_label(format!("Add a '{prop}' property here."))
+            .with_help(format!("Declare it as '{prop}?' to make it optional."))
+            .finish(),
+            Le::PropType { expected, found } => MsgBuilder::build_err(
+                format!("Expected a property of type '{expected}', got type '{found}'"),
+                self.span,
+            )
+            .with_code("T0213")
+            .with_err_label("This value has the wrong type.")
+            .finish(),
+            Le::RecursiveComponent(name) => {
+                MsgBuilder::build_err(format!("Component '{name}' uses itself"), self.span)
+                    .with_code("T0214")
+                    .with_err_label("This is inside of the body of the same component.")
+                    .with_note(
+                        "Components are expanded when loading, so they can not be recursive.",
+                    )
+                    .finish()
+            }
+            Le::MisplacedChildren => {
+                MsgBuilder::build_err("'children' outside of a component", self.span)
+                    .with_code("T0215")
+                    .with_err_label("There are no children to render here.")
+                    .with_note(
+                        "'<children/>' renders the children given to the component it is in.",
+                    )
+                    .finish()
+            }
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use indexmap::IndexMap;
+
+use crate::{
+    ast::{component::ComponentDef, nodes::*, parser::Parser, schema::ElementSchema},
+    filestore::{FileStore, cycle_chain},
+    lang_errors::{LangMessage, LangResult},
+    spans::{IntoSpanned, Span, Spanned},
+};
+
+mod component;
+mod error;
+pub use component::{CHILDREN_TAG, COMPONENT_TAG};
+pub use error::*;
+
+pub type Result<T = Vec<Spanned<Node>>> = LangResult<T>;
+fn err<T>(value: impl LangMessage + 'static) -> Result<T> {
+    Err(value.into())
+}
+pub const INCLUDE_TAG: &str = "include";
+pub const EXTENDS_TAG: &str = "extends";
+pub const BLOCK_TAG: &str = "block";
+/// Placeholder for the content a block is overriding.
+pub const PARENT_TAG: &str = "parent";
+
+/// Loads templates from disk, splices `<include>`d files into the tree,
+/// applies `<extends>` layouts and expands components.
+///
+/// Every loaded file is registered in the [`FileStore`], so errors inside of it
+/// are reported against that file.
+pub struct Loader<'a> {
+    file_store: &'a mut FileStore,
+    schema: &'a ElementSchema,
+    /// Canonical paths of the files currently being loaded, used to detect cycles.
+    stack: Vec<PathBuf>,
+    /// Components that can be used, a component has to be defined before it is used.
+    components: HashMap<String, ComponentDef>,
+    /// Names of the components whose body is being expanded, used to detect recursion.
+    expanding: Vec<String>,
+}
+impl<'a> Loader<'a> {
+    pub fn new(file_store: &'a mut FileStore, schema: &'a ElementSchema) -> Self {
+        Self {
+            file_store,
+            schema,
+            stack: vec![],
+            components: HashMap::new(),
+            expanding: vec![],
+        }
+    }
+    /// Makes `components` usable in the templates this loader loads.
+    pub fn with_components(self, components: HashMap<String, ComponentDef>) -> Self {
+        Self { components, ..self }
+    }
+    /// Loads and parses the template at `path` with its includes and layouts resolved.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result {
+        let path = path.as_ref();
+        let canonical = fs::canonicalize(path)?;
+        let text = fs::read_to_string(path)?;
+        let nodes = self.load_source(text, path, canonical)?;
+        flatten_blocks(nodes)
+    }
+    /// Resolves the includes and layouts of already parsed `nodes`.
+    ///
+    /// Relative paths are resolved from `dir`.
+    pub fn resolve(&mut self, nodes: Vec<Spanned<Node>>, dir: &Path) -> Result {
+        let nodes = self.resolve_layout(nodes, dir)?;
+        flatten_blocks(nodes)
+    }
+    /// Expands the includes in `nodes` and applies the layout they extend, if any.
+    ///
+    /// `<block>` elements are kept, so templates extending this one can still override them.
+    fn resolve_layout(&mut self, nodes: Vec<Spanned<Node>>, dir: &Path) -> Result {
+        let nodes = self.expand(nodes, dir)?;
+        let Some(extends) = find_extends(&nodes)? else {
+            return Ok(nodes);
+        };
+        self.extend(extends, dir)
+    }
+    fn expand(&mut self, nodes: Vec<Spanned<Node>>, dir: &Path) -> Result {
+        let mut expanded = Vec::with_capacity(nodes.len());
+        for mut node in nodes {
+            match node.item {
+                Node::Element(element) if element.name == INCLUDE_TAG => {
+                    let (path, src_span) = source_path(&element, dir)?;
+                    let mut included = self.load_path(&path, src_span, element.start_tag_span)?;
+                    expanded.append(&mut included);
+                    continue;
+                }
+                Node::Element(element) if element.name == COMPONENT_TAG => {
+                    self.define_element(element, dir)?;
+                    continue;
+                }
+                Node::Element(element) if self.is_component(&element.name) => {
+                    let call = self.call(element, dir)?;
+                    expanded.push(Node::Component(call).to_spanned(node.span));
+                    continue;
+                }
+                _ => {}
+            }
+            for children in node.item.children_mut() {
+                *children = self.expand(std::mem::take(children), dir)?;
+            }
+            expanded.push(node);
+        }
+        Ok(expanded)
+    }
+    /// Loads the layout `extends` points to and overrides its blocks with the ones in `extends`.
+    fn extend(&mut self, extends: Element, dir: &Path) -> Result {
+        let (path, src_span) = source_path(&extends, dir)?;
+        let mut overrides: IndexMap<String, Spanned<Element>> = IndexMap::new();
+        for child in extends.children {
+            let block = match child.item {
+                Node::Element(element) if element.name == BLOCK_TAG => element,
+                Node::Text(text) if text.trim().is_empty() => continue,
+                Node::Comment(_) => continue,
+                _ => return err(LoadError::ContentOutsideBlock.to_spanned(child.span)),
+            };
+            let name = block_name(&block)?;
+            if let Some(first) = overrides.get(&name) {
+                return err(LoadError::DuplicateBlock {
+                    name,
+                    first: first.start_tag_span,
+                }
+                .to_spanned(block.start_tag_span));
+            }
+            overrides.insert(name, block.to_spanned(child.span));
+        }
+        let layout = self.load_path(&path, src_span, extends.start_tag_span)?;
+        let layout = apply_overrides(layout, &mut overrides)?;
+        // The first block in source order is reported, so the error is the same on every run
+        if let Some(unused) = overrides.into_values().next() {
+            let name = block_name(&unused)?;
+            return err(LoadError::UnknownBlock(name).to_spanned(unused.start_tag_span));
+        }
+        Ok(layout)
+    }
+    /// Loads the file at `path`, `src_span` is the span of the path in the template
+    /// loading it, and `tag_span` the span of the tag doing so.
+    fn load_path(&mut self, path: &Path, src_span: Span, tag_span: Span) -> Result {
+        let not_found = |err: std::io::Error| {
+            LoadError::NotFound {
+                path: path.display().to_string(),
+                reason: err.to_string(),
+            }
+            .to_spanned(src_span)
+        };
+        let canonical = fs::canonicalize(path).map_err(not_found)?;
+        if let Some(chain) = cycle_chain(&self.stack, &canonical) {
+            return err(LoadError::Cycle(chain).to_spanned(tag_span));
+        }
+        let text = fs::read_to_string(path).map_err(not_found)?;
+        self.load_source(text, path, canonical)
+    }
+    fn load_source(&mut self, text: String, path: &Path, canonical: PathBuf) -> Result {
+        let file_id = self
+            .file_store
+            .add_named(text.clone(), path.display().to_string());
+        let nodes = Parser::make(&text, file_id, self.schema.clone()).parse()?;
+        let dir = path.parent().unwrap_or(Path::new("")).to_owned();
+        self.stack.push(canonical);
+        let resolved = self.resolve_layout(nodes, &dir);
+        self.stack.pop();
+        resolved
+    }
+}
+/// Resolves the `src` of a loading element like `<include>` against `dir`.
+fn source_path(element: &Element, dir: &Path) -> Result<(PathBuf, Span)> {
+    match element.props.get("src").map(|prop| &prop.value) {
+        Some(Spanned {
+            item: Value::String(src),
+            span,
+        }) => Ok((dir.join(src), *span)),
+        _ => err(LoadError::MissingSource(element.name.clone()).to_spanned(element.start_tag_span)),
+    }
+}
+fn block_name(block: &Element) -> Result<String> {
+    match block.props.get("name").map(|prop| &prop.value) {
+        Some(Spanned {
+            item: Value::String(name),
+            ..
+        }) => Ok(name.clone()),
+        _ => err(LoadError::MissingBlockName.to_spanned(block.start_tag_span)),
+    }
+}
+/// Finds the `<extends>` of a template, which has to be the only thing in it.
+fn find_extends(nodes: &[Spanned<Node>]) -> Result<Option<Element>> {
+    let is_extends =
+        |node: &Spanned<Node>| matches!(&node.item, Node::Element(el) if el.name == EXTENDS_TAG);
+    let Some(extends) = nodes.iter().find(|node| is_extends(node)) else {
+        return Ok(None);
+    };
+    for node in nodes {
+        match &node.item {
+            _ if std::ptr::eq(node, extends) => {}
+            Node::Text(text) if text.trim().is_empty() => {}
+            Node::Comment(_) => {}
+            _ => return err(LoadError::ContentOutsideBlock.to_spanned(node.span)),
+        }
+    }
+    let Node::Element(element) = &extends.item else {
+        unreachable!("Only elements are matched")
+    };
+    Ok(Some(element.clone()))
+}
+/// Replaces the content of blocks in `nodes` with the ones in `overrides`, taking the used ones out of it.
+fn apply_overrides(
+    nodes: Vec<Spanned<Node>>,
+    overrides: &mut IndexMap<String, Spanned<Element>>,
+) -> Result {
+    let mut applied = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        if let Node::Element(block) = &mut node.item
+            && block.name == BLOCK_TAG
+            && let Some(replacement) = overrides.shift_remove(&block_name(block)?)
+        {
+            let default = std::mem::take(&mut block.children);
+            block.children = replace_parent(replacement.item.children, &default);
+        }
+        for children in node.item.children_mut() {
+            *children = apply_overrides(std::mem::take(children), overrides)?;
+        }
+        applied.push(node);
+    }
+    Ok(applied)
+}
+/// Replaces every `<parent/>` in `nodes` with `default`.
+fn replace_parent(nodes: Vec<Spanned<Node>>, default: &[Spanned<Node>]) -> Vec<Spanned<Node>> {
+    let mut replaced = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        if let Node::Element(element) = &node.item
+            && element.name == PARENT_TAG
+        {
+            replaced.extend_from_slice(default);
+            continue;
+        }
+        for children in node.item.children_mut() {
+            *children = replace_parent(std::mem::take(children), default);
+        }
+        replaced.push(node);
+    }
+    replaced
+}
+/// Replaces the remaining `<block>` elements with their content once every layout was applied.
+///
+/// Also catches placeholders that were not replaced, since they are not inside of what they refer to.
+fn flatten_blocks(nodes: Vec<Spanned<Node>>) -> Result {
+    let mut flattened = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        match &mut node.item {
+            Node::Element(element) if element.name == PARENT_TAG => {
+                return err(LoadError::MisplacedParent.to_spanned(element.start_tag_span));
+            }
+            Node::Element(element) if element.name == CHILDREN_TAG => {
+                return err(LoadError::MisplacedChildren.to_spanned(element.start_tag_span));
+            }
+            Node::Element(element) if element.name == BLOCK_TAG => {
+                let children = std::mem::take(&mut element.children);
+                flattened.append(&mut flatten_blocks(children)?);
+                continue;
+            }
+            _ => {}
+        }
+        for children in node.item.children_mut() {
+            *children = flatten_blocks(std::mem::take(children))?;
+        }
+        flattened.push(node);
+    }
+    Ok(flattened)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Compiler, filestore::FileStore, lang_errors::LangError};
+
+    #[test]
+    fn reports_first_unknown_block() {
+        let dir = std::env::temp_dir().join(format!("loader-blocks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("layout.html"), r#"<block name="main"></block>"#).unwrap();
+        let page = r#"<extends src="layout.html"><block name="zeta"></block><block name="alpha"></block><block name="beta"></block></extends>"#;
+        fs::write(dir.join("page.html"), page).unwrap();
+        for _ in 0..4 {
+            let error = Compiler::make(FileStore::new(), true)
+                .parse_file(dir.join("page.html"))
+                .expect_err("The blocks are not in the layout");
+            let LangError::Compiler(msg) = error else {
+                panic!("Expected a compiler error, got {error}");
+            };
+            let diagnostic = msg.diagnostic();
+            assert_eq!(diagnostic.code.as_deref(), Some("T0207"));
+            assert_eq!(diagnostic.message, "The layout has no block 'zeta'");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,22 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use std::fmt::{Debug, Display};
+use std::fmt::Debug;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::io::{self, Write};
 use template_engine::Compiler;
+use template_engine::ast::schema::ElementSchema;
+use template_engine::diagnostic::{self, MessageFormat};
+use serde_json::Value as JsonValue;
+use template_engine::render::IoSink;
+use template_engine::spans::SpanText;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// The code string or a filepath to execute.
     content: Option<String>,
 
@@ -17,46 +26,132 @@ struct Args {
     /// If specified, print the output of a compiler stage instead of executing.
     #[arg(short, long, value_enum)]
     stage: Option<Stage>,
+    /// Path to an element schema in json, toml or yaml, merged on top of the built-in html5 one while parsing.
+    #[arg(long)]
+    schema: Option<PathBuf>,
+    /// Reject unknown fields in the rules of the schema instead of ignoring them.
+    #[arg(long, requires = "schema")]
+    strict_schema: bool,
+    /// Keep parsing after errors, reporting every one and printing the partial ast.
+    #[arg(long)]
+    recover: bool,
+    /// Path to a json file whose fields are the variables used when rendering.
+    #[arg(short, long)]
+    data: Option<PathBuf>,
+    /// How errors are printed to stderr, `json` prints one object per line for tools to read.
+    #[arg(long, value_enum, default_value_t = Format::Human)]
+    message_format: Format,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the JSON Schema of schema files, editors can use it to check and complete them.
+    JsonSchema,
+    /// Print the long-form explanation of an error code, like `T0102`.
+    Explain { code: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Human,
+    Json,
+}
+impl From<Format> for MessageFormat {
+    fn from(value: Format) -> Self {
+        match value {
+            Format::Human => Self::Human,
+            Format::Json => Self::Json,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Stage {
     Lexer,
     Ast,
+    Html,
 }
 
-fn print_if_ok<T: Debug, E>(result: Result<T, E>) {
-    if let Ok(value) = result {
-        println!("{value:#?}");
+/// Prints the value of `result`, its error was already reported by the compiler.
+fn print_if_ok<T: Debug, E>(result: Result<T, E>) -> ExitCode {
+    match result {
+        Ok(value) => {
+            println!("{value:#?}");
+            ExitCode::SUCCESS
+        }
+        Err(_) => ExitCode::FAILURE,
     }
 }
 
-fn display_if_ok<T: Display, E>(result: Result<T, E>) {
-    if let Ok(value) = result {
-        println!("{value}");
-    }
-}
 /// Runs a specific compiler stage on the given content.
 /// `is_expr` should be true for REPL-like single expressions.
-fn run_stage(compiler: &mut Compiler, stage: &Stage, content: &str) {
+/// `path` is where `content` was read from, it is used to resolve included files.
+/// `recover` parses the ast without stopping at the first error.
+///
+/// Fails when an error was reported.
+fn run_stage(
+    compiler: &mut Compiler,
+    stage: &Stage,
+    content: &str,
+    path: Option<&Path>,
+    data: &JsonValue,
+    recover: bool,
+) -> ExitCode {
     match stage {
         Stage::Lexer => {
             let Ok(tokens) = compiler.lex(content) else {
-                return;
+                return ExitCode::FAILURE;
             };
+            let text = SpanText::new(content);
             for token in tokens {
-                let token_value = content[token.span.start..token.span.end].to_string();
+                let token_value = text.get(token.span);
                 println!("{token:?} = {token_value:?}");
             }
+            ExitCode::SUCCESS
+        }
+        Stage::Ast if recover => {
+            let (nodes, errors) = match path {
+                Some(path) => compiler.parse_file_recovering(path),
+                None => compiler.parse_recovering(content),
+            };
+            println!("{nodes:#?}");
+            if errors.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Stage::Ast => match path {
+            Some(path) => print_if_ok(compiler.parse_file(path)),
+            None => print_if_ok(compiler.parse(content)),
+        },
+        Stage::Html => {
+            let sink = IoSink(io::BufWriter::new(io::stdout().lock()));
+            let rendered = match path {
+                Some(path) => compiler.render_file_to(path, data, sink),
+                None => compiler.render_to(content, data, sink),
+            };
+            match rendered {
+                Ok(_) => {
+                    println!();
+                    ExitCode::SUCCESS
+                }
+                Err(_) => ExitCode::FAILURE,
+            }
         }
-        Stage::Ast => print_if_ok(compiler.parse(content)),
     }
 }
 
 /// Processes a single input string or file.
-fn run_once(args: &Args, compiler: &mut Compiler, content: String) {
+fn run_once(
+    args: &Args,
+    compiler: &mut Compiler,
+    content: String,
+    data: &JsonValue,
+) -> ExitCode {
+    let path = (!args.is_code).then(|| Path::new(&content));
     let code = if args.is_code {
-        Ok(content)
+        Ok(content.clone())
     } else {
         fs::read_to_string(&content)
     };
@@ -64,18 +159,19 @@ fn run_once(args: &Args, compiler: &mut Compiler, content: String) {
     match code {
         Ok(code) => {
             if let Some(stage) = &args.stage {
-                run_stage(compiler, stage, &code);
+                run_stage(compiler, stage, &code, path, data, args.recover)
             } else {
-                let _ = run_stage(compiler, &Stage::Ast, &code);
+                run_stage(compiler, &Stage::Ast, &code, path, data, args.recover)
             }
         }
         Err(e) => {
-            eprintln!("Error reading input: {e}");
+            compiler.report(&format!("Could not read the input: {e}").into());
+            ExitCode::FAILURE
         }
     }
 }
-/// Starts an interactive Read-Eval-Print-Loop (REPL).
-fn run_repl(compiler: &mut Compiler, stage: Option<Stage>) {
+/// Starts an interactive Read-Eval-Print-Loop (REPL), errors are reported but do not end it.
+fn run_repl(compiler: &mut Compiler, stage: Option<Stage>, data: &JsonValue) {
     println!("Shlang REPL. Enter an empty line or press Ctrl+C to exit.");
     loop {
         print!(">: ");
@@ -86,20 +182,68 @@ fn run_repl(compiler: &mut Compiler, stage: Option<Stage>) {
         }
 
         if let Some(ref stage) = stage {
-            run_stage(compiler, stage, line.trim());
+            run_stage(compiler, stage, line.trim(), None, data, false);
         } else {
-            run_stage(compiler, &Stage::Ast, line.trim());
+            run_stage(compiler, &Stage::Ast, line.trim(), None, data, false);
         }
     }
 }
 
-fn main() {
+fn load_data(path: &PathBuf) -> Result<JsonValue, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Exits with a failure when an error was reported or the explained code does not exist.
+fn main() -> ExitCode {
     let args = Args::parse();
-    let mut compiler = Compiler::new();
+    match &args.command {
+        Some(Command::JsonSchema) => {
+            println!("{:#}", ElementSchema::json_schema());
+            return ExitCode::SUCCESS;
+        }
+        Some(Command::Explain { code }) => {
+            return match diagnostic::explain(code) {
+                Some(explanation) => {
+                    print!("{explanation}");
+                    ExitCode::SUCCESS
+                }
+                None => {
+                    eprintln!("Error: '{code}' is not an error code.");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        None => {}
+    }
+    let mut compiler = Compiler::new().with_message_format(args.message_format.into());
+    if let Some(path) = &args.schema {
+        let loaded = if args.strict_schema {
+            compiler.with_strict_schema_file(path)
+        } else {
+            compiler.with_schema_file(path)
+        };
+        compiler = match loaded {
+            Ok(compiler) => compiler,
+            // The compiler already reported it
+            Err(_) => return ExitCode::FAILURE,
+        };
+    }
 
+    let data = match &args.data {
+        Some(path) => match load_data(path) {
+            Ok(data) => data,
+            Err(e) => {
+                compiler.report(&format!("Could not load the data: {e}").into());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => JsonValue::Object(Default::default()),
+    };
     if let Some(content) = args.content.clone() {
-        run_once(&args, &mut compiler, content);
+        run_once(&args, &mut compiler, content, &data)
     } else {
-        run_repl(&mut compiler, args.stage);
+        run_repl(&mut compiler, args.stage, &data);
+        ExitCode::SUCCESS
     }
 }
@@ -0,0 +1,117 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::{
+    ast::{component::ComponentDef, nodes::Node},
+    lang_errors::LangResult,
+    render::{Context, escape::escape_text},
+    spans::Spanned,
+};
+
+/// The evaluated properties given to a native component.
+pub type Props = Map<String, JsonValue>;
+/// A component implemented in Rust, it writes its output through the [`RenderCtx`].
+///
+/// It is shareable across threads, so a compiler can be built once and used by all of them.
+pub type NativeComponent =
+    Arc<dyn Fn(&Props, Children, &mut RenderCtx) -> LangResult<()> + Send + Sync>;
+
+/// The components usable in templates, by the name of the element using them.
+///
+/// Template components are expanded when loading, while native ones are called
+/// by the renderer every time their element is rendered.
+#[derive(Clone, Default)]
+pub struct ComponentRegistry {
+    templates: HashMap<String, ComponentDef>,
+    natives: HashMap<String, NativeComponent>,
+}
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Registers a native component, replacing any component with the same name.
+    pub fn register<F>(&mut self, name: impl Into<String>, component: F)
+    where
+        F: Fn(&Props, Children, &mut RenderCtx) -> LangResult<()> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.templates.remove(&name);
+        self.natives.insert(name, Arc::new(component));
+    }
+    /// Registers a template component, replacing any component with the same name.
+    pub fn add_template(&mut self, def: ComponentDef) {
+        self.natives.remove(&def.name);
+        self.templates.insert(def.name.clone(), def);
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name) || self.natives.contains_key(name)
+    }
+    pub fn template(&self, name: &str) -> Option<&ComponentDef> {
+        self.templates.get(name)
+    }
+    pub fn native(&self, name: &str) -> Option<&NativeComponent> {
+        self.natives.get(name)
+    }
+    pub fn templates(&self) -> &HashMap<String, ComponentDef> {
+        &self.templates
+    }
+    pub fn natives(&self) -> &HashMap<String, NativeComponent> {
+        &self.natives
+    }
+}
+impl Debug for ComponentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentRegistry")
+            .field("templates", &self.templates)
+            .field("natives", &self.natives.keys())
+            .finish()
+    }
+}
+/// The children a native component was given, they are only rendered through [`RenderCtx::render`].
+#[derive(Debug, Clone, Copy)]
+pub struct Children<'a> {
+    nodes: &'a [Spanned<Node>],
+}
+impl<'a> Children<'a> {
+    pub fn new(nodes: &'a [Spanned<Node>]) -> Self {
+        Self { nodes }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    pub fn nodes(&self) -> &'a [Spanned<Node>] {
+        self.nodes
+    }
+}
+/// What the renderer exposes to native components, independent of its sink.
+pub(super) trait ComponentOutput {
+    fn write(&mut self, text: &str) -> LangResult<()>;
+    fn render_nodes(&mut self, nodes: &[Spanned<Node>]) -> LangResult<()>;
+    fn context(&self) -> &Context;
+}
+/// Gives a native component access to the output and the variables of the template using it.
+pub struct RenderCtx<'r> {
+    out: &'r mut dyn ComponentOutput,
+}
+impl<'r> RenderCtx<'r> {
+    pub(super) fn new(out: &'r mut dyn ComponentOutput) -> Self {
+        Self { out }
+    }
+    /// Writes `html` into the output as is, without escaping it.
+    pub fn write_html(&mut self, html: &str) -> LangResult<()> {
+        self.out.write(html)
+    }
+    /// Writes `text` into the output, escaped as element content.
+    pub fn write_text(&mut self, text: &str) -> LangResult<()> {
+        self.out.write(&escape_text(text))
+    }
+    /// Renders the children of the component in the template that gave them.
+    pub fn render(&mut self, children: Children) -> LangResult<()> {
+        self.out.render_nodes(children.nodes)
+    }
+    /// Looks up a variable of the template using the component.
+    pub fn lookup(&self, name: &str) -> Option<&JsonValue> {
+        self.out.context().lookup(name)
+    }
+}
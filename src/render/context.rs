@@ -0,0 +1,85 @@
+use serde::{Serialize, de::Error};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::lang_errors::LangResult;
+
+/// The data a template is rendered against.
+///
+/// Lookups walk the scopes from the innermost one outwards, so names bound by
+/// nested constructs shadow the ones given by the caller.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    scopes: Vec<Map<String, JsonValue>>,
+}
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Map::new()],
+        }
+    }
+    pub fn from_map(root: Map<String, JsonValue>) -> Self {
+        Self { scopes: vec![root] }
+    }
+    /// Serializes `data` into the root scope, its fields become the template's variables.
+    ///
+    /// `data` is serialized once up front, so it has to serialize into a map like a struct does.
+    pub fn from_serialize(data: &impl Serialize) -> LangResult<Self> {
+        match serde_json::to_value(data)? {
+            JsonValue::Object(root) => Ok(Self::from_map(root)),
+            other => {
+                let kind = type_name(&other);
+                let msg = format!("Render data must serialize into a map, got type '{kind}'");
+                Err(serde_json::Error::custom(msg).into())
+            }
+        }
+    }
+    pub fn lookup(&self, name: &str) -> Option<&JsonValue> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+    /// Binds `name` in the innermost scope.
+    pub fn set(&mut self, name: impl Into<String>, value: JsonValue) {
+        if self.scopes.is_empty() {
+            self.scopes.push(Map::new());
+        }
+        let scope = self.scopes.last_mut().expect("A scope was just pushed");
+        scope.insert(name.into(), value);
+    }
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Map::new());
+    }
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
+/// The name of a value's type as shown in error messages.
+pub fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "bool",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+/// Converts a value to the text that gets written into the page.
+pub fn stringify(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+/// Whether a value counts as true in conditions.
+///
+/// `null`, `false`, `0`, and empty strings, arrays and objects are false.
+pub fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(cond) => *cond,
+        JsonValue::Number(num) => num.as_f64().is_some_and(|num| num != 0.0),
+        JsonValue::String(text) => !text.is_empty(),
+        JsonValue::Array(items) => !items.is_empty(),
+        JsonValue::Object(map) => !map.is_empty(),
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    ast::{
+        component::PropType,
+        expr::{BinaryOp, UnaryOp},
+    },
+    diagnostic::Diagnostic,
+    lang_errors::{LangMessage, MsgBuilder},
+    spans::*,
+};
+#[derive(Debug, Clone)]
+pub enum RenderError {
+    VoidElementChildren(String),
+    UndefinedVariable(String),
+    InvalidMember {
+        target: &'static str,
+        field: String,
+    },
+    InvalidIndex {
+        target: &'static str,
+        index: &'static str,
+    },
+    InvalidOperand {
+        op: UnaryOp,
+        operand: &'static str,
+    },
+    InvalidOperands {
+        op: BinaryOp,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    DivisionByZero,
+    NotIterable(&'static str),
+    PropType {
+        prop: String,
+        expected: PropType,
+        found: &'static str,
+    },
+    UnknownFilter(String),
+    FilterArity {
+        name: String,
+        expected: RangeInclusive<usize>,
+        found: usize,
+    },
+    /// A filter returned an error that has no location of its own.
+    FilterFailed {
+        name: String,
+        reason: String,
+    },
+    /// A native component returned an error that has no location of its own.
+    ComponentFailed {
+        name: String,
+        reason: String,
+    },
+    /// A placeholder left where a recovering parse could not parse the content.
+    UnparsedContent,
+}
+impl LangMessage for Spanned<RenderError> {
+    fn diagnostic(&self) -> Diagnostic {
+        use RenderError as Re;
+        match &self.item {
+            Re::VoidElementChildren(name) => {
+                MsgBuilder::build_err(format!("Void element '{name}' has children"), self.span)
+                    .with_code("T0401")
+                    .with_err_label("This element can not contain anything.")
+                    .with_help(format!("Close '{name}' right away with '<{name}/>'."))
+                    .finish()
+            }
+            Re::UndefinedVariable(name) => {
+                MsgBuilder::build_err(format!("Undefined variable '{name}'"), self.span)
+                    .with_code("T0402")
+                    .with_err_label("This was not found in the render context.")
+                    .finish()
+            }
+            Re::InvalidMember { target, field } => MsgBuilder::build_err(
+                format!("Can not access '{field}' on type '{target}'"),
+                self.span,
+            )
+            .with_code("T0403")
+            .with_err_label("Only objects have fields.")
+            .finish(),
+            Re::InvalidIndex { target, index } => MsgBuilder::build_err(
+                format!("Can not index type '{target}' with type '{index}'"),
+                self.span,
+            )
+            .with_code("T0404")
+            .with_err_label("This index is invalid.")
+            .with_note("Arrays are indexed by numbers and objects by strings.")
+            .finish(),
+            Re::InvalidOperand { op, operand } => MsgBuilder::build_err(
+                format!("Can not apply '{op}' to type '{operand}'"),
+                self.span,
+            )
+            .with_code("T0405")
+            .with_err_label("This operation is invalid.")
+            .finish(),
+            Re::InvalidOperands { op, lhs, rhs } => MsgBuilder::build_err(
+                format!("Can not apply '{op}' to types '{lhs}' and '{rhs}'"),
+                self.span,
+            )
+            .with_code("T0406")
+            .with_err_label("This operation is invalid.")
+            .finish(),
+            Re::NotIterable(kind) => {
+                MsgBuilder::build_err(format!("Can not iterate over type '{kind}'"), self.span)
+                    .with_code("T0408")
+                    .with_err_label("This should be an array or an object.")
+                    .finish()
+            }
+            Re::DivisionByZero => MsgBuilder::build_err("Division by zero", self.span)
+                .with_code("T0407")
+                .with_err_label("The right side of this evaluates to zero.")
+                .finish(),
+            Re::PropType {
+                prop,
+                expected,
+                found,
+            } => MsgBuilder::build_err(
+                format!("Property '{prop}' expects type '{expected}', got type '{found}'"),
+                self.span,
+            )
+            .with_code("T0409")
+            .with_err_label("This evaluates to the wrong type.")
+            .finish(),
+            Re::UnknownFilter(name) => {
+                MsgBuilder::build_err(format!("Unknown filter '{name}'"), self.span)
+                    .with_code("T0410")
+                    .with_err_label("No filter with this name was registered.")
+                    .finish()
+            }
+            Re::FilterArity {
+                name,
+                expected,
+                found,
+            } => {
+                let expected = match (expected.start(), expected.end()) {
+                    (min, max) if min == max => format!("{min}"),
+                    (min, max) => format!("{min} to {max}"),
+                };
+                MsgBuilder::build_err(
+                    format!("Filter '{name}' takes {expected} arguments, got {found}"),
+                    self.span,
+                )
+                .with_code("T0411")
+                .with_err_label("This is called with the wrong amount of arguments.")
+                .finish()
+            }
+            Re::FilterFailed { name, reason } => {
+                MsgBuilder::build_err(format!("Filter '{name}' failed"), self.span)
+                    .with_code("T0412")
+                    .with_err_label(reason)
+                    .finish()
+            }
+            Re::ComponentFailed { name, reason } => {
+                MsgBuilder::build_err(format!("Component '{name}' failed"), self.span)
+                    .with_code("T0413")
+                    .with_err_label(reason)
+                    .finish()
+            }
+            Re::UnparsedContent => MsgBuilder::build_err("Content that failed to parse", self.span)
+                .with_code("T0414")
+                .with_err_label("This could not be parsed.")
+                .with_note("Templates with parse errors can not be rendered.")
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use serde_json::Value as JsonValue;
+
+use crate::render::stringify;
+
+/// Elements that never have content and are written without an end tag.
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose content is not html, like scripts and stylesheets.
+pub const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+/// Attributes holding a url, values written into them can not use schemes that run code.
+pub const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "poster",
+    "cite",
+    "background",
+    "longdesc",
+    "manifest",
+    "xlink:href",
+];
+/// Url schemes values in url attributes may use.
+pub const SAFE_URL_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "ftp"];
+/// Written instead of a url whose scheme is not in [`SAFE_URL_SCHEMES`].
+pub const BLOCKED_URL: &str = "about:invalid#blocked";
+
+pub fn is_void_element(name: &str) -> bool {
+    VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+pub fn is_raw_text_element(name: &str) -> bool {
+    RAW_TEXT_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+/// Where a value is written, each place needs its own escaping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapeContext {
+    /// Element content parsed as html.
+    #[default]
+    Text,
+    /// The content of a raw element that is neither a script nor a stylesheet.
+    RawText,
+    /// A quoted attribute value.
+    Attribute,
+    /// A quoted attribute value holding a url like `href`.
+    Url,
+    /// The content of a `<script>`.
+    Script,
+    /// The content of a `<style>`.
+    Style,
+}
+impl EscapeContext {
+    /// The context of the content of the element `name`, `raw` is set when its content is not html.
+    pub fn for_content(name: &str, raw: bool) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "script" => Self::Script,
+            "style" => Self::Style,
+            _ if raw => Self::RawText,
+            _ => Self::Text,
+        }
+    }
+    pub fn for_attribute(name: &str) -> Self {
+        if URL_ATTRIBUTES.contains(&name.to_ascii_lowercase().as_str()) {
+            Self::Url
+        } else {
+            Self::Attribute
+        }
+    }
+    /// Whether text written by the template author is html that has to be escaped in this context.
+    pub fn escapes_literals(&self) -> bool {
+        matches!(self, Self::Text)
+    }
+    /// Converts `value` into text that can be written in this context.
+    pub fn escape(&self, value: &JsonValue) -> String {
+        match self {
+            Self::Text | Self::RawText => escape_text(&stringify(value)),
+            Self::Attribute => escape_attr(&stringify(value)),
+            Self::Url => escape_attr(&sanitize_url(&stringify(value))),
+            Self::Script => escape_script(value),
+            Self::Style => escape_style(&stringify(value)),
+        }
+    }
+}
+/// Escapes text that is written as element content.
+pub fn escape_text(text: &str) -> String {
+    let mut buffer = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            _ => buffer.push(ch),
+        }
+    }
+    buffer
+}
+/// Escapes text that is written inside a double quoted attribute value.
+pub fn escape_attr(text: &str) -> String {
+    let mut buffer = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '"' => buffer.push_str("&quot;"),
+            '\'' => buffer.push_str("&#39;"),
+            _ => buffer.push(ch),
+        }
+    }
+    buffer
+}
+/// Writes `value` as a javascript literal, so it can not end the script or inject code.
+pub fn escape_script(value: &JsonValue) -> String {
+    let json = value.to_string();
+    let mut buffer = String::with_capacity(json.len());
+    for ch in json.chars() {
+        match ch {
+            '<' => buffer.push_str("\\u003c"),
+            '>' => buffer.push_str("\\u003e"),
+            '&' => buffer.push_str("\\u0026"),
+            '\u{2028}' => buffer.push_str("\\u2028"),
+            '\u{2029}' => buffer.push_str("\\u2029"),
+            _ => buffer.push(ch),
+        }
+    }
+    buffer
+}
+/// Escapes text written inside a stylesheet, only keeping characters that can not change its structure.
+pub fn escape_style(text: &str) -> String {
+    let mut buffer = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            ch if ch.is_alphanumeric() => buffer.push(ch),
+            ' ' | '#' | '.' | ',' | '%' | '-' | '_' => buffer.push(ch),
+            ch => buffer.push_str(&format!("\\{:x} ", ch as u32)),
+        }
+    }
+    buffer
+}
+/// Replaces urls with an unsafe scheme like `javascript:` with [`BLOCKED_URL`] and
+/// percent encodes characters that are not allowed in urls.
+pub fn sanitize_url(url: &str) -> String {
+    let url = url.trim();
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    if let Some(scheme) = scheme
+        && !SAFE_URL_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+    {
+        return BLOCKED_URL.to_owned();
+    }
+    let mut buffer = String::with_capacity(url.len());
+    for ch in url.chars() {
+        match ch {
+            ch if ch.is_ascii_graphic() && !matches!(ch, '"' | '\'' | '<' | '>' | '`') => {
+                buffer.push(ch)
+            }
+            ch => {
+                let mut bytes = [0; 4];
+                for byte in ch.encode_utf8(&mut bytes).bytes() {
+                    buffer.push_str(&format!("%{byte:02X}"));
+                }
+            }
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn escapes_text_and_attributes() {
+        assert_eq!(
+            escape_text(r#"<a href="x">&</a>"#),
+            r#"&lt;a href="x"&gt;&amp;&lt;/a&gt;"#
+        );
+        assert_eq!(escape_attr(r#""'<>&"#), "&quot;&#39;&lt;&gt;&amp;");
+    }
+
+    #[test]
+    fn script_values_can_not_end_the_script() {
+        assert_eq!(
+            escape_script(&json!("</script><b>")),
+            r#""\u003c/script\u003e\u003cb\u003e""#
+        );
+        assert_eq!(escape_script(&json!({"a": [1, true]})), r#"{"a":[1,true]}"#);
+        assert_eq!(escape_script(&json!("a\u{2028}b")), r#""a\u2028b""#);
+    }
+
+    #[test]
+    fn style_values_can_not_leave_the_declaration() {
+        assert_eq!(escape_style("red"), "red");
+        assert_eq!(escape_style("red;}body{x"), r"red\3b \7d body\7b x");
+        assert_eq!(escape_style("</style>"), r"\3c \2f style\3e ");
+    }
+
+    #[test]
+    fn keeps_safe_urls() {
+        assert_eq!(
+            sanitize_url("https://example.com/a?b=c#d"),
+            "https://example.com/a?b=c#d"
+        );
+        assert_eq!(
+            sanitize_url("MAILTO:me@example.com"),
+            "MAILTO:me@example.com"
+        );
+        assert_eq!(
+            sanitize_url("/relative/path:with-colon"),
+            "/relative/path:with-colon"
+        );
+        assert_eq!(sanitize_url("?q=a:b"), "?q=a:b");
+        assert_eq!(sanitize_url("a b\"<"), "a%20b%22%3C");
+    }
+
+    #[test]
+    fn blocks_unsafe_schemes() {
+        let unsafe_urls = [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "  javascript:alert(1)",
+            "\njavascript:alert(1)",
+            "java\tscript:alert(1)",
+            "\u{1}javascript:alert(1)",
+            "vbscript:msgbox(1)",
+            "data:text/html,<script>alert(1)</script>",
+        ];
+        for url in unsafe_urls {
+            assert_eq!(sanitize_url(url), BLOCKED_URL, "{url:?} should be blocked");
+        }
+    }
+
+    #[test]
+    fn escapes_for_each_context() {
+        let value = json!("javascript:alert(1)");
+        assert_eq!(EscapeContext::Url.escape(&value), BLOCKED_URL);
+        assert_eq!(
+            EscapeContext::Attribute.escape(&value),
+            "javascript:alert(1)"
+        );
+        assert_eq!(EscapeContext::for_attribute("HREF"), EscapeContext::Url);
+        assert_eq!(
+            EscapeContext::for_content("script", true),
+            EscapeContext::Script
+        );
+        assert_eq!(
+            EscapeContext::for_content("style", true),
+            EscapeContext::Style
+        );
+        assert_eq!(
+            EscapeContext::for_content("title", true),
+            EscapeContext::RawText
+        );
+    }
+}
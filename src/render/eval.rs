@@ -0,0 +1,312 @@
+use std::borrow::Cow;
+
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::expr::{BinaryOp, Expr, UnaryOp},
+    lang_errors::LangError,
+    spans::{IntoSpanned, Span, Spanned},
+};
+
+use super::{
+    HtmlRenderer, RenderError, Result, Sink,
+    context::{is_truthy, stringify, type_name},
+    err,
+    filters::{DEFAULT_FILTER, Filter},
+};
+
+/// Takes `key` out of a possibly borrowed value, so borrowed data is never cloned whole.
+fn take_field<'a>(
+    value: Cow<'a, JsonValue>,
+    get: impl FnOnce(&JsonValue) -> Option<&JsonValue>,
+) -> Cow<'a, JsonValue> {
+    match value {
+        Cow::Borrowed(value) => match get(value) {
+            Some(found) => Cow::Borrowed(found),
+            None => Cow::Owned(JsonValue::Null),
+        },
+        Cow::Owned(value) => Cow::Owned(get(&value).cloned().unwrap_or_default()),
+    }
+}
+/// A number taken out of a [`JsonValue`], integers are kept exact when possible.
+#[derive(Clone, Copy)]
+enum Num {
+    Int(i64),
+    Float(f64),
+}
+impl Num {
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        let JsonValue::Number(num) = value else {
+            return None;
+        };
+        match num.as_i64() {
+            Some(int) => Some(Self::Int(int)),
+            None => num.as_f64().map(Self::Float),
+        }
+    }
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(int) => int as f64,
+            Self::Float(float) => float,
+        }
+    }
+    /// Applies `int_op` when both sides are integers and it does not overflow, `float_op` otherwise.
+    fn apply(
+        self,
+        rhs: Self,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> JsonValue {
+        if let (Self::Int(a), Self::Int(b)) = (self, rhs)
+            && let Some(result) = int_op(a, b)
+        {
+            return JsonValue::from(result);
+        }
+        JsonValue::from(float_op(self.as_f64(), rhs.as_f64()))
+    }
+}
+/// Equality where `1` and `1.0` are the same number.
+pub fn loose_eq(lhs: &JsonValue, rhs: &JsonValue) -> bool {
+    match (Num::from_json(lhs), Num::from_json(rhs)) {
+        (Some(a), Some(b)) => a.as_f64() == b.as_f64(),
+        _ => lhs == rhs,
+    }
+}
+impl<W: Sink> HtmlRenderer<W> {
+    pub(super) fn eval(&self, expr: &Spanned<Expr>) -> Result<Cow<'_, JsonValue>> {
+        self.eval_inner(expr, false)
+    }
+    /// Pipes `value` through the filter `name`, errors of the filter are reported at its name.
+    fn eval_filter(
+        &self,
+        value: &Spanned<Expr>,
+        name: &Spanned<String>,
+        args: &[Spanned<Expr>],
+        lenient: bool,
+    ) -> Result<Cow<'_, JsonValue>> {
+        let filter = self.find_filter(name, args.len())?;
+        let value = self.eval_inner(value, lenient || name.item == DEFAULT_FILTER)?;
+        self.apply_filter(filter, &value, name, args)
+            .map(Cow::Owned)
+    }
+    /// Pipes an already evaluated `value` through the filter `name`.
+    pub(super) fn pipe(
+        &self,
+        value: &JsonValue,
+        name: &Spanned<String>,
+        args: &[Spanned<Expr>],
+    ) -> Result<JsonValue> {
+        let filter = self.find_filter(name, args.len())?;
+        self.apply_filter(filter, value, name, args)
+    }
+    /// The filter `name`, when it can be called with `arg_count` arguments.
+    fn find_filter(&self, name: &Spanned<String>, arg_count: usize) -> Result<&Filter> {
+        let Some(filter) = self.filters.get(&name.item) else {
+            return err(RenderError::UnknownFilter(name.item.clone()).to_spanned(name.span));
+        };
+        if !filter.args.contains(&arg_count) {
+            return err(RenderError::FilterArity {
+                name: name.item.clone(),
+                expected: filter.args.clone(),
+                found: arg_count,
+            }
+            .to_spanned(name.span));
+        }
+        Ok(filter)
+    }
+    fn apply_filter(
+        &self,
+        filter: &Filter,
+        value: &JsonValue,
+        name: &Spanned<String>,
+        args: &[Spanned<Expr>],
+    ) -> Result<JsonValue> {
+        let args = args
+            .iter()
+            .map(|arg| self.eval(arg).map(Cow::into_owned))
+            .collect::<Result<Vec<_>>>()?;
+        let reason = match filter.apply(value, &args) {
+            Ok(filtered) => return Ok(filtered),
+            Err(LangError::Other(reason)) => reason.to_string(),
+            Err(LangError::Serde(reason)) => reason.to_string(),
+            Err(other) => return Err(other),
+        };
+        err(RenderError::FilterFailed {
+            name: name.item.clone(),
+            reason,
+        }
+        .to_spanned(name.span))
+    }
+    /// Evaluates `expr`, when `lenient` is set undefined variables and fields of
+    /// null evaluate to null instead of failing.
+    fn eval_inner(&self, expr: &Spanned<Expr>, lenient: bool) -> Result<Cow<'_, JsonValue>> {
+        match &expr.item {
+            Expr::Literal(value) => Ok(Cow::Owned(value.to_json())),
+            Expr::Variable(name) => match self.ctx.lookup(name) {
+                Some(value) => Ok(Cow::Borrowed(value)),
+                None if lenient => Ok(Cow::Owned(JsonValue::Null)),
+                None => err(RenderError::UndefinedVariable(name.clone()).to_spanned(expr.span)),
+            },
+            Expr::Unary { op, expr: operand } => {
+                let value = self.eval(operand)?;
+                self.eval_unary(op, &value, expr.span)
+            }
+            Expr::Binary { op, lhs, rhs } => self.eval_binary(op, lhs, rhs, expr.span),
+            Expr::Ternary {
+                cond,
+                then,
+                otherwise,
+            } => {
+                if is_truthy(self.eval(cond)?.as_ref()) {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Safe(value) => self.eval_inner(value, lenient),
+            Expr::Filter { value, name, args } => self.eval_filter(value, name, args, lenient),
+            Expr::Member { object, field } => {
+                let object = self.eval_inner(object, lenient)?;
+                if lenient && object.is_null() {
+                    return Ok(object);
+                }
+                if !object.is_object() {
+                    return err(RenderError::InvalidMember {
+                        target: type_name(&object),
+                        field: field.item.clone(),
+                    }
+                    .to_spanned(field.span));
+                }
+                Ok(take_field(object, |value| value.get(&field.item)))
+            }
+            Expr::Index { object, index } => {
+                let object = self.eval_inner(object, lenient)?;
+                let key = self.eval(index)?;
+                match (object.as_ref(), key.as_ref()) {
+                    (JsonValue::Null, _) if lenient => Ok(object),
+                    (JsonValue::Array(_), JsonValue::Number(num)) if num.is_u64() => {
+                        let idx = num.as_u64().unwrap_or_default() as usize;
+                        Ok(take_field(object, |value| value.get(idx)))
+                    }
+                    (JsonValue::Object(_), JsonValue::String(key)) => {
+                        let key = key.clone();
+                        Ok(take_field(object, |value| value.get(&key)))
+                    }
+                    (object, key) => err(RenderError::InvalidIndex {
+                        target: type_name(object),
+                        index: type_name(key),
+                    }
+                    .to_spanned(index.span)),
+                }
+            }
+        }
+    }
+    fn eval_unary(
+        &self,
+        op: &Spanned<UnaryOp>,
+        value: &JsonValue,
+        span: Span,
+    ) -> Result<Cow<'_, JsonValue>> {
+        let result = match (op.item, Num::from_json(value)) {
+            (UnaryOp::Not, _) => JsonValue::Bool(!is_truthy(value)),
+            (UnaryOp::Neg, Some(Num::Int(int))) => match int.checked_neg() {
+                Some(neg) => JsonValue::from(neg),
+                None => JsonValue::from(-(int as f64)),
+            },
+            (UnaryOp::Neg, Some(Num::Float(float))) => JsonValue::from(-float),
+            (UnaryOp::Neg, None) => {
+                return err(RenderError::InvalidOperand {
+                    op: op.item,
+                    operand: type_name(value),
+                }
+                .to_spanned(span));
+            }
+        };
+        Ok(Cow::Owned(result))
+    }
+    fn eval_binary(
+        &self,
+        op: &Spanned<BinaryOp>,
+        lhs: &Spanned<Expr>,
+        rhs: &Spanned<Expr>,
+        span: Span,
+    ) -> Result<Cow<'_, JsonValue>> {
+        use BinaryOp as B;
+        // These only evaluate the right side when they need to
+        match op.item {
+            B::And => {
+                let lhs = self.eval(lhs)?;
+                return if is_truthy(&lhs) {
+                    self.eval(rhs)
+                } else {
+                    Ok(lhs)
+                };
+            }
+            B::Or => {
+                let lhs = self.eval(lhs)?;
+                return if is_truthy(&lhs) {
+                    Ok(lhs)
+                } else {
+                    self.eval(rhs)
+                };
+            }
+            B::Coalesce => {
+                let lhs = self.eval_inner(lhs, true)?;
+                return if lhs.is_null() {
+                    self.eval(rhs)
+                } else {
+                    Ok(lhs)
+                };
+            }
+            _ => {}
+        }
+        let lhs = self.eval(lhs)?;
+        let rhs = self.eval(rhs)?;
+        let invalid = || {
+            err(RenderError::InvalidOperands {
+                op: op.item,
+                lhs: type_name(&lhs),
+                rhs: type_name(&rhs),
+            }
+            .to_spanned(span))
+        };
+        let nums = Num::from_json(&lhs).zip(Num::from_json(&rhs));
+        let result = match (op.item, nums) {
+            (B::Eq, _) => JsonValue::Bool(loose_eq(&lhs, &rhs)),
+            (B::NotEq, _) => JsonValue::Bool(!loose_eq(&lhs, &rhs)),
+            (B::Add, None) if lhs.is_string() || rhs.is_string() => {
+                JsonValue::String(stringify(&lhs) + &stringify(&rhs))
+            }
+            (B::Add, Some((a, b))) => a.apply(b, i64::checked_add, |a, b| a + b),
+            (B::Sub, Some((a, b))) => a.apply(b, i64::checked_sub, |a, b| a - b),
+            (B::Mul, Some((a, b))) => a.apply(b, i64::checked_mul, |a, b| a * b),
+            (B::Div | B::Rem, Some((_, b))) if b.as_f64() == 0.0 => {
+                return err(RenderError::DivisionByZero.to_spanned(span));
+            }
+            (B::Div, Some((a, b))) => a.apply(
+                b,
+                |a, b| (a.checked_rem(b)? == 0).then(|| a.checked_div(b))?,
+                |a, b| a / b,
+            ),
+            (B::Rem, Some((a, b))) => a.apply(b, i64::checked_rem, |a, b| a % b),
+            (B::Lesser | B::LesserEq | B::Greater | B::GreaterEq, _) => {
+                let ordering = match (nums, lhs.as_ref(), rhs.as_ref()) {
+                    (Some((a, b)), _, _) => a.as_f64().partial_cmp(&b.as_f64()),
+                    (None, JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+                    _ => return invalid(),
+                };
+                let Some(ordering) = ordering else {
+                    return Ok(Cow::Owned(JsonValue::Bool(false)));
+                };
+                JsonValue::Bool(match op.item {
+                    B::Lesser => ordering.is_lt(),
+                    B::LesserEq => ordering.is_le(),
+                    B::Greater => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                })
+            }
+            _ => return invalid(),
+        };
+        Ok(Cow::Owned(result))
+    }
+}
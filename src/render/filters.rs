@@ -0,0 +1,242 @@
+use std::{collections::HashMap, fmt::Debug, ops::RangeInclusive, sync::Arc};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::expr::{ESCAPE_FILTER, SAFE_FILTER},
+    lang_errors::LangResult,
+    render::{escape::escape_attr, stringify, type_name},
+};
+
+/// Filter whose input may be undefined, it is evaluated like the left side of `??`.
+pub const DEFAULT_FILTER: &str = "default";
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Implementation of a filter, called with the piped value and the evaluated arguments.
+///
+/// It is shareable across threads, so a compiler can be built once and used by all of them.
+pub type FilterFn = Arc<dyn Fn(&JsonValue, &[JsonValue]) -> LangResult<JsonValue> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Filter {
+    /// How many arguments the filter can be called with.
+    pub args: RangeInclusive<usize>,
+    pub func: FilterFn,
+}
+impl Filter {
+    pub fn new(
+        args: RangeInclusive<usize>,
+        func: impl Fn(&JsonValue, &[JsonValue]) -> LangResult<JsonValue> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            args,
+            func: Arc::new(func),
+        }
+    }
+    pub fn apply(&self, value: &JsonValue, args: &[JsonValue]) -> LangResult<JsonValue> {
+        (self.func)(value, args)
+    }
+}
+/// The filters usable through pipes like `{name | upper}`, by name.
+#[derive(Clone)]
+pub struct FilterRegistry {
+    filters: HashMap<String, Filter>,
+}
+impl Default for FilterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl FilterRegistry {
+    /// Creates a registry with the built-in filters.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register("upper", 0..=0, |value, _| {
+            Ok(stringify(value).to_uppercase().into())
+        });
+        registry.register("lower", 0..=0, |value, _| {
+            Ok(stringify(value).to_lowercase().into())
+        });
+        registry.register("capitalize", 0..=0, |value, _| {
+            Ok(capitalize(&stringify(value)).into())
+        });
+        registry.register("title", 0..=0, |value, _| {
+            let text = stringify(value);
+            let words: Vec<String> = text.split(' ').map(capitalize).collect();
+            Ok(words.join(" ").into())
+        });
+        registry.register("trim", 0..=0, |value, _| Ok(stringify(value).trim().into()));
+        registry.register("truncate", 1..=2, truncate);
+        registry.register("join", 0..=1, join);
+        registry.register("length", 0..=0, length);
+        registry.register(DEFAULT_FILTER, 1..=1, |value, args| match value {
+            JsonValue::Null => Ok(args[0].clone()),
+            other => Ok(other.clone()),
+        });
+        registry.register("date", 0..=1, date);
+        registry.register("number", 0..=1, number);
+        registry.register("json", 0..=0, |value, _| {
+            Ok(serde_json::to_string(value)?.into())
+        });
+        registry.register(ESCAPE_FILTER, 0..=0, |value, _| {
+            Ok(escape_attr(&stringify(value)).into())
+        });
+        // Only reached with arguments, `| safe` is parsed into a safe expression
+        registry.register(SAFE_FILTER, 0..=0, |value, _| Ok(value.clone()));
+        registry
+    }
+    /// Creates a registry without any filters.
+    pub fn empty() -> Self {
+        Self {
+            filters: HashMap::new(),
+        }
+    }
+    /// Registers a filter callable with a number of arguments in `args`, replacing any filter with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        args: RangeInclusive<usize>,
+        func: impl Fn(&JsonValue, &[JsonValue]) -> LangResult<JsonValue> + Send + Sync + 'static,
+    ) {
+        self.filters.insert(name.into(), Filter::new(args, func));
+    }
+    pub fn get(&self, name: &str) -> Option<&Filter> {
+        self.filters.get(name)
+    }
+}
+impl Debug for FilterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.filters.keys()).finish()
+    }
+}
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+/// Reads the argument at `index` as a count, if it was given.
+fn count_arg(args: &[JsonValue], index: usize) -> LangResult<Option<usize>> {
+    match args.get(index) {
+        None => Ok(None),
+        Some(JsonValue::Number(num)) if num.is_u64() => Ok(num.as_u64().map(|num| num as usize)),
+        Some(other) => {
+            let kind = type_name(other);
+            Err(format!(
+                "Argument {} should be a positive int, got type '{kind}'",
+                index + 1
+            )
+            .into())
+        }
+    }
+}
+/// Shortens text to `length` characters, appending the second argument or `...` when it was cut.
+fn truncate(value: &JsonValue, args: &[JsonValue]) -> LangResult<JsonValue> {
+    let length = count_arg(args, 0)?.unwrap_or_default();
+    let text = stringify(value);
+    if text.chars().count() <= length {
+        return Ok(text.into());
+    }
+    let end = args
+        .get(1)
+        .map(stringify)
+        .unwrap_or_else(|| "...".to_owned());
+    let mut truncated: String = text.chars().take(length).collect();
+    truncated.push_str(&end);
+    Ok(truncated.into())
+}
+/// Joins the items of an array with the argument or `, ` between them.
+fn join(value: &JsonValue, args: &[JsonValue]) -> LangResult<JsonValue> {
+    let separator = args
+        .first()
+        .map(stringify)
+        .unwrap_or_else(|| ", ".to_owned());
+    match value {
+        JsonValue::Array(items) => {
+            let items: Vec<String> = items.iter().map(stringify).collect();
+            Ok(items.join(&separator).into())
+        }
+        JsonValue::Null => Ok("".into()),
+        other => Err(format!("Can only join arrays, got type '{}'", type_name(other)).into()),
+    }
+}
+fn length(value: &JsonValue, _: &[JsonValue]) -> LangResult<JsonValue> {
+    let length = match value {
+        JsonValue::String(text) => text.chars().count(),
+        JsonValue::Array(items) => items.len(),
+        JsonValue::Object(map) => map.len(),
+        JsonValue::Null => 0,
+        other => {
+            return Err(format!("Type '{}' has no length", type_name(other)).into());
+        }
+    };
+    Ok(length.into())
+}
+/// Formats a date with a strftime like format, dates are unix timestamps in
+/// seconds, RFC 3339 strings or plain `YYYY-MM-DD` dates.
+fn date(value: &JsonValue, args: &[JsonValue]) -> LangResult<JsonValue> {
+    let format = args.first().map(stringify);
+    let format = format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+    let date = match value {
+        JsonValue::Number(num) => num
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|date| date.naive_utc()),
+        JsonValue::String(text) => DateTime::parse_from_rfc3339(text)
+            .map(|date| date.naive_local())
+            .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|date| date.into()))
+            .ok(),
+        JsonValue::Null => return Ok(JsonValue::Null),
+        other => {
+            return Err(format!("Can not format type '{}' as a date", type_name(other)).into());
+        }
+    };
+    let Some(date) = date else {
+        return Err(format!("'{}' is not a valid date", stringify(value)).into());
+    };
+    let mut formatted = String::new();
+    std::fmt::Write::write_fmt(&mut formatted, format_args!("{}", date.format(format)))
+        .map_err(|_| format!("'{format}' is not a valid date format"))?;
+    Ok(formatted.into())
+}
+/// Formats a number with `,` between thousands, rounded to the argument's amount of decimals if given.
+fn number(value: &JsonValue, args: &[JsonValue]) -> LangResult<JsonValue> {
+    let decimals = count_arg(args, 0)?;
+    let num = match value {
+        JsonValue::Number(num) => num,
+        JsonValue::Null => return Ok(JsonValue::Null),
+        other => {
+            return Err(format!("Can not format type '{}' as a number", type_name(other)).into());
+        }
+    };
+    let text = match decimals {
+        Some(decimals) => format!("{:.decimals$}", num.as_f64().unwrap_or_default()),
+        None => num.to_string(),
+    };
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text.as_str()),
+    };
+    let (int, fraction) = match text.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None => (text, None),
+    };
+    let mut grouped = String::from(sign);
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    Ok(grouped.into())
+}
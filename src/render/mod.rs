@@ -0,0 +1,479 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use indexmap::IndexMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::{
+        expr::{ESCAPE_FILTER, Expr},
+        nodes::*,
+        schema::ElementSchema,
+    },
+    lang_errors::{LangError, LangMessage, LangResult},
+    spans::{IntoSpanned, Span, Spanned},
+};
+
+mod component;
+mod context;
+mod error;
+pub mod escape;
+mod eval;
+pub mod filters;
+mod sink;
+use component::ComponentOutput;
+pub use component::{Children, ComponentRegistry, NativeComponent, Props, RenderCtx};
+pub use context::*;
+pub use error::*;
+use escape::*;
+use filters::FilterRegistry;
+pub use sink::*;
+
+pub type Result<T = ()> = LangResult<T>;
+/// The variable holding the `index`, `first`, `last` and `length` of the innermost `<for>`.
+pub const LOOP_VARIABLE: &str = "loop";
+fn loop_metadata(index: usize, length: usize) -> JsonValue {
+    serde_json::json!({
+        "index": index,
+        "first": index == 0,
+        "last": index + 1 == length,
+        "length": length,
+    })
+}
+/// Whether `expr` chooses how it is escaped, by piping its value through `safe` or `escape`.
+///
+/// The filters after them work on the text they give, which is not escaped again.
+fn is_escaped(expr: &Expr) -> bool {
+    match expr {
+        Expr::Safe(_) => true,
+        Expr::Filter { value, name, args } => {
+            (name.item == ESCAPE_FILTER && args.is_empty()) || is_escaped(&value.item)
+        }
+        _ => false,
+    }
+}
+/// Renders `nodes` into a [`String`].
+pub fn render_to_string(nodes: &[Spanned<Node>]) -> Result<String> {
+    HtmlRenderer::new().render(nodes)
+}
+/// Streams the rendered `nodes` into `writer`, returning it once everything was written.
+pub fn render_to_io<W: std::io::Write>(nodes: &[Spanned<Node>], writer: W) -> Result<W> {
+    let sink = HtmlRenderer::from_sink(IoSink(writer)).render(nodes)?;
+    Ok(sink.into_inner())
+}
+/// Streams the rendered `nodes` into `writer`, returning it once everything was written.
+pub fn render_to_fmt<W: std::fmt::Write>(nodes: &[Spanned<Node>], writer: W) -> Result<W> {
+    let sink = HtmlRenderer::from_sink(FmtSink(writer)).render(nodes)?;
+    Ok(sink.into_inner())
+}
+fn err<T>(value: impl LangMessage + 'static) -> Result<T> {
+    Err(value.into())
+}
+/// Walks a parsed template and writes it out as html.
+///
+/// Output is written into the [`Sink`] as soon as it is produced, so nothing but
+/// the sink itself has to hold the whole document.
+#[derive(Default)]
+pub struct HtmlRenderer<W: Sink = String> {
+    out: W,
+    ctx: Context,
+    /// The contexts of the templates using the components being rendered, slots are rendered in them.
+    callers: Vec<Context>,
+    /// Native components, called instead of writing the elements named after them.
+    components: HashMap<String, NativeComponent>,
+    filters: FilterRegistry,
+    /// Used to find the raw elements whose content is not escaped as html.
+    schema: ElementSchema,
+    /// Where the content being rendered ends up, values are escaped for it.
+    context: EscapeContext,
+}
+impl<W: Sink + Debug> Debug for HtmlRenderer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HtmlRenderer")
+            .field("out", &self.out)
+            .field("ctx", &self.ctx)
+            .field("callers", &self.callers)
+            .field("components", &self.components.keys())
+            .field("filters", &self.filters)
+            .field("schema", &self.schema)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self::from_sink(String::new())
+    }
+}
+impl<W: Sink> HtmlRenderer<W> {
+    pub fn from_sink(out: W) -> Self {
+        Self {
+            out,
+            ctx: Context::new(),
+            callers: vec![],
+            components: HashMap::new(),
+            filters: FilterRegistry::new(),
+            schema: ElementSchema::new(),
+            context: EscapeContext::Text,
+        }
+    }
+    /// Sets the data that expressions in the template are evaluated against.
+    pub fn with_context(self, ctx: Context) -> Self {
+        Self { ctx, ..self }
+    }
+    /// Sets the native components that elements named after them are rendered with.
+    pub fn with_components(self, components: HashMap<String, NativeComponent>) -> Self {
+        Self { components, ..self }
+    }
+    /// Sets the schema, elements it parses raw have their content escaped like scripts.
+    pub fn with_schema(self, schema: ElementSchema) -> Self {
+        Self { schema, ..self }
+    }
+    /// Sets the filters usable in expressions, replacing the built-in ones.
+    pub fn with_filters(self, filters: FilterRegistry) -> Self {
+        Self { filters, ..self }
+    }
+    /// Renders `nodes` into the sink and returns it.
+    pub fn render(mut self, nodes: &[Spanned<Node>]) -> Result<W> {
+        self.render_nodes(nodes)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+    fn write(&mut self, text: &str) -> Result {
+        self.out.write_str(text)
+    }
+    fn render_nodes(&mut self, nodes: &[Spanned<Node>]) -> Result {
+        for node in nodes {
+            self.render_node(node)?;
+        }
+        Ok(())
+    }
+    fn render_node(&mut self, node: &Spanned<Node>) -> Result {
+        match &node.item {
+            Node::Text(text) if self.context.escapes_literals() => {
+                self.write(&escape_text(text))?
+            }
+            Node::Text(text) | Node::Raw(text) => self.write(text)?,
+            Node::Comment(_) => {}
+            Node::Element(element) => self.render_element(element, node.span)?,
+            Node::Interpolation(expr) => {
+                let text = self.eval_escaped(expr, self.context)?;
+                self.write(&text)?;
+            }
+            Node::If {
+                branches,
+                else_branch,
+            } => self.render_if(branches, else_branch.as_ref())?,
+            Node::For(for_loop) => self.render_for(for_loop)?,
+            Node::Component(call) => self.render_component(call)?,
+            Node::Slot(children) => self.render_slot(children)?,
+            Node::Error => return err(RenderError::UnparsedContent.to_spanned(node.span)),
+        }
+        Ok(())
+    }
+    fn render_if(
+        &mut self,
+        branches: &[Spanned<IfBranch>],
+        else_branch: Option<&Spanned<Vec<Spanned<Node>>>>,
+    ) -> Result {
+        for branch in branches {
+            if is_truthy(self.eval(&branch.cond)?.as_ref()) {
+                return self.render_nodes(&branch.children);
+            }
+        }
+        match else_branch {
+            Some(children) => self.render_nodes(children),
+            None => Ok(()),
+        }
+    }
+    fn render_for(&mut self, for_loop: &ForLoop) -> Result {
+        // The collection is cloned since the context is modified while iterating it
+        let items: Vec<(JsonValue, JsonValue)> = match self.eval(&for_loop.each)?.into_owned() {
+            JsonValue::Array(items) => items
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| (JsonValue::from(index), item))
+                .collect(),
+            JsonValue::Object(map) => map
+                .into_iter()
+                .map(|(key, item)| (JsonValue::String(key), item))
+                .collect(),
+            JsonValue::Null => vec![],
+            other => {
+                return err(
+                    RenderError::NotIterable(type_name(&other)).to_spanned(for_loop.each.span)
+                );
+            }
+        };
+        if items.is_empty() {
+            return match &for_loop.empty {
+                Some(children) => self.render_nodes(children),
+                None => Ok(()),
+            };
+        }
+        let length = items.len();
+        for (position, (key, item)) in items.into_iter().enumerate() {
+            self.ctx.push_scope();
+            self.ctx.set(&for_loop.binding.item, item);
+            if let Some(index) = &for_loop.index {
+                self.ctx.set(&index.item, key);
+            }
+            self.ctx.set(LOOP_VARIABLE, loop_metadata(position, length));
+            let rendered = self.render_nodes(&for_loop.children);
+            self.ctx.pop_scope();
+            rendered?;
+        }
+        Ok(())
+    }
+    /// Renders the body of a component with only its properties in scope.
+    fn render_component(&mut self, call: &ComponentCall) -> Result {
+        let mut props = serde_json::Map::with_capacity(call.props.len());
+        for prop in &call.props {
+            let value = self.eval(&prop.value)?.into_owned();
+            if !prop.ty.accepts(&value) {
+                return err(RenderError::PropType {
+                    prop: prop.name.clone(),
+                    expected: prop.ty,
+                    found: type_name(&value),
+                }
+                .to_spanned(prop.value.span));
+            }
+            props.insert(prop.name.clone(), value);
+        }
+        let caller = std::mem::replace(&mut self.ctx, Context::from_map(props));
+        self.callers.push(caller);
+        let rendered = self.render_nodes(&call.body);
+        self.ctx = self.callers.pop().expect("The caller was just pushed");
+        rendered
+    }
+    /// Renders the children given to a component in the context of the template that gave them.
+    fn render_slot(&mut self, children: &[Spanned<Node>]) -> Result {
+        let Some(caller) = self.callers.pop() else {
+            return self.render_nodes(children);
+        };
+        let component = std::mem::replace(&mut self.ctx, caller);
+        let rendered = self.render_nodes(children);
+        let caller = std::mem::replace(&mut self.ctx, component);
+        self.callers.push(caller);
+        rendered
+    }
+    /// Calls a native component, errors it returns without a location are reported at its start tag.
+    fn render_native(&mut self, component: NativeComponent, element: &Element) -> Result {
+        let mut props = Props::with_capacity(element.props.len());
+        for (name, Prop { value, .. }) in &element.props {
+            let value = match &value.item {
+                Value::Expr(expr) => self.eval(expr)?.into_owned(),
+                literal => literal.to_json(),
+            };
+            props.insert(name.clone(), value);
+        }
+        let children = Children::new(&element.children);
+        let reason = match component(&props, children, &mut RenderCtx::new(self)) {
+            Err(LangError::Other(reason)) => reason.to_string(),
+            Err(LangError::Serde(reason)) => reason.to_string(),
+            other => return other,
+        };
+        err(RenderError::ComponentFailed {
+            name: element.name.clone(),
+            reason,
+        }
+        .to_spanned(element.start_tag_span))
+    }
+    fn render_element(&mut self, element: &Element, span: Span) -> Result {
+        let name = &element.name;
+        if let Some(component) = self.components.get(name) {
+            return self.render_native(component.clone(), element);
+        }
+        self.write("<")?;
+        self.write(name)?;
+        self.render_props(&element.props)?;
+        self.write(">")?;
+        let rules = self.schema.get_rule(name);
+        if is_void_element(name) || rules.is_some_and(|rules| rules.void) {
+            if !element.children.is_empty() {
+                return err(RenderError::VoidElementChildren(name.clone()).to_spanned(span));
+            }
+            return Ok(());
+        }
+        let raw = is_raw_text_element(name) || rules.is_some_and(|rules| rules.parse_raw);
+        let parent_context = self.context;
+        self.context = EscapeContext::for_content(name, raw);
+        let rendered = self.render_nodes(&element.children);
+        self.context = parent_context;
+        rendered?;
+        self.write("</")?;
+        self.write(name)?;
+        self.write(">")?;
+        Ok(())
+    }
+    /// Evaluates `expr` into text that can be written in `context`.
+    ///
+    /// `safe` values are written as they are. A value piped through `escape` is escaped
+    /// for `context` instead of as html, so urls in it are still checked, and what the filters
+    /// after it make of the escaped text is written as it is.
+    fn eval_escaped(&mut self, expr: &Spanned<Expr>, context: EscapeContext) -> Result<String> {
+        if !is_escaped(&expr.item) {
+            return Ok(context.escape(self.eval(expr)?.as_ref()));
+        }
+        Ok(stringify(&self.eval_escaped_value(expr, context)?))
+    }
+    /// Evaluates an expression that chooses how it is escaped, see [`is_escaped`].
+    fn eval_escaped_value(
+        &self,
+        expr: &Spanned<Expr>,
+        context: EscapeContext,
+    ) -> Result<JsonValue> {
+        match &expr.item {
+            Expr::Filter { value, name, args } if name.item == ESCAPE_FILTER && args.is_empty() => {
+                Ok(context.escape(self.eval(value)?.as_ref()).into())
+            }
+            Expr::Filter { value, name, args } if is_escaped(&value.item) => {
+                let value = self.eval_escaped_value(value, context)?;
+                self.pipe(&value, name, args)
+            }
+            _ => Ok(self.eval(expr)?.into_owned()),
+        }
+    }
+    fn render_props(&mut self, props: &IndexMap<String, Prop>) -> Result {
+        for (name, Prop { value, .. }) in props {
+            // `None` is written as a bare attribute like `hidden`, other values are already escaped.
+            // Literals are html written by the template author, they are kept as written and only their quotes are escaped
+            let value = match &value.item {
+                Value::Bool(true) => None,
+                Value::Bool(false) | Value::Null | Value::Element => continue,
+                Value::Int(num) => Some(num.to_string()),
+                Value::Float(num) => Some(num.to_string()),
+                Value::String(text) => Some(text.replace('"', "&quot;")),
+                Value::Expr(expr) if is_escaped(&expr.item) => {
+                    Some(self.eval_escaped(expr, EscapeContext::for_attribute(name))?)
+                }
+                Value::Expr(expr) => match self.eval(expr)?.as_ref() {
+                    JsonValue::Bool(true) => None,
+                    JsonValue::Bool(false) | JsonValue::Null => continue,
+                    other => Some(EscapeContext::for_attribute(name).escape(other)),
+                },
+            };
+            self.write(" ")?;
+            self.write(name)?;
+            if let Some(value) = value {
+                self.write("=\"")?;
+                self.write(&value)?;
+                self.write("\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+impl<W: Sink> ComponentOutput for HtmlRenderer<W> {
+    fn write(&mut self, text: &str) -> Result {
+        self.out.write_str(text)
+    }
+    fn render_nodes(&mut self, nodes: &[Spanned<Node>]) -> Result {
+        HtmlRenderer::render_nodes(self, nodes)
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{Compiler, filestore::FileStore};
+
+    fn render(template: &str, data: serde_json::Value) -> String {
+        Compiler::make(FileStore::new(), true)
+            .render(template, &data)
+            .expect("The template should render")
+    }
+
+    #[test]
+    fn escape_filter_checks_urls() {
+        let data = json!({"url": "javascript:alert(1)"});
+        assert_eq!(
+            render("<a href={url | escape}>x</a>", data.clone()),
+            r#"<a href="about:invalid#blocked">x</a>"#
+        );
+        assert_eq!(
+            render("<a href={url}>x</a>", data),
+            r#"<a href="about:invalid#blocked">x</a>"#
+        );
+    }
+
+    #[test]
+    fn escape_filter_escapes_once() {
+        let data = json!({"text": "<b>&</b>"});
+        assert_eq!(
+            render("<p>{text | escape}</p>", data),
+            "<p>&lt;b&gt;&amp;&lt;/b&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn filters_after_escape_are_not_escaped_again() {
+        let data = json!({"text": "<b>&</b>", "url": "JAVASCRIPT:alert(1)"});
+        assert_eq!(
+            render(r#"<p>{text | escape | default("-")}</p>"#, data.clone()),
+            "<p>&lt;b&gt;&amp;&lt;/b&gt;</p>"
+        );
+        assert_eq!(
+            render("<p>{text | escape | upper}</p>", data.clone()),
+            "<p>&LT;B&GT;&AMP;&LT;/B&GT;</p>"
+        );
+        assert_eq!(
+            render("<a href={url | escape | lower}>x</a>", data.clone()),
+            r#"<a href="about:invalid#blocked">x</a>"#
+        );
+        assert_eq!(
+            render("<p>{text | safe | upper}</p>", data),
+            "<p><B>&</B></p>"
+        );
+    }
+
+    #[test]
+    fn safe_values_are_not_escaped() {
+        let data = json!({"html": "<b>hi</b>"});
+        assert_eq!(render("<p>{html | safe}</p>", data), "<p><b>hi</b></p>");
+    }
+
+    #[test]
+    fn interpolates_scripts() {
+        let data = json!({"name": "</script><b>", "count": 3});
+        let template = "<script>var x = {name}; var n = {count};</script>";
+        assert_eq!(
+            render(template, data),
+            r#"<script>var x = "\u003c/script\u003e\u003cb\u003e"; var n = 3;</script>"#
+        );
+    }
+
+    #[test]
+    fn keeps_script_braces() {
+        let template = "<script>if (a) { go(); } function f() { return {a: 1}; }</script>";
+        assert_eq!(render(template, json!({})), template);
+    }
+
+    #[test]
+    fn interpolates_styles() {
+        let data = json!({"color": "red;}body{x"});
+        let template = "<style>p { color: {color}; }</style>";
+        assert_eq!(
+            render(template, data),
+            r"<style>p { color: red\3b \7d body\7b x; }</style>"
+        );
+    }
+
+    #[test]
+    fn keeps_literal_attributes_as_written() {
+        let template = r#"<a title="a&quot;b &amp; c" class='x"y'>t</a>"#;
+        assert_eq!(
+            render(template, json!({})),
+            r#"<a title="a&quot;b &amp; c" class="x&quot;y">t</a>"#
+        );
+        let data = json!({"title": "a&quot;b"});
+        assert_eq!(
+            render("<a title={title}>t</a>", data),
+            r#"<a title="a&amp;quot;b">t</a>"#
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{fmt, io};
+
+use crate::lang_errors::{LangError, LangResult};
+
+/// A destination the renderer writes html into as it walks the tree.
+///
+/// Use [`IoSink`] to stream into a [`std::io::Write`] and [`FmtSink`] for a
+/// [`std::fmt::Write`]. [`String`] can be used directly.
+pub trait Sink {
+    fn write_str(&mut self, text: &str) -> LangResult<()>;
+    /// Called once the whole template was written.
+    fn flush(&mut self) -> LangResult<()> {
+        Ok(())
+    }
+}
+impl Sink for String {
+    fn write_str(&mut self, text: &str) -> LangResult<()> {
+        self.push_str(text);
+        Ok(())
+    }
+}
+impl<T: Sink + ?Sized> Sink for &mut T {
+    fn write_str(&mut self, text: &str) -> LangResult<()> {
+        (**self).write_str(text)
+    }
+    fn flush(&mut self) -> LangResult<()> {
+        (**self).flush()
+    }
+}
+/// Adapts a [`std::io::Write`] into a [`Sink`].
+#[derive(Debug)]
+pub struct IoSink<W: io::Write>(pub W);
+impl<W: io::Write> IoSink<W> {
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+impl<W: io::Write> Sink for IoSink<W> {
+    fn write_str(&mut self, text: &str) -> LangResult<()> {
+        self.0.write_all(text.as_bytes())?;
+        Ok(())
+    }
+    fn flush(&mut self) -> LangResult<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+/// Adapts a [`std::fmt::Write`] into a [`Sink`].
+///
+/// [`std::fmt::Error`] carries no information, so failures are reported as a generic
+/// [`LangError::Io`].
+#[derive(Debug)]
+pub struct FmtSink<W: fmt::Write>(pub W);
+impl<W: fmt::Write> FmtSink<W> {
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+impl<W: fmt::Write> Sink for FmtSink<W> {
+    fn write_str(&mut self, text: &str) -> LangResult<()> {
+        self.0
+            .write_str(text)
+            .map_err(|_| LangError::Io(io::Error::other("Could not write to formatter")))
+    }
+}
@@ -172,3 +172,31 @@ impl ariadne::Span for Span {
         self.start - self.end
     }
 }
+/// A text sliced by spans, which count chars while `str` is sliced by bytes.
+#[derive(Debug, Clone)]
+pub struct SpanText<'a> {
+    text: &'a str,
+    /// The byte offset of every char, empty when the text is ascii since they are the same.
+    bytes: Vec<usize>,
+}
+impl<'a> SpanText<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let bytes = if text.is_ascii() {
+            vec![]
+        } else {
+            text.char_indices().map(|(byte, _)| byte).collect()
+        };
+        Self { text, bytes }
+    }
+    /// The byte offset of the char at `offset`, offsets past the end are the end of the text.
+    fn byte(&self, offset: usize) -> usize {
+        if self.bytes.is_empty() {
+            return offset.min(self.text.len());
+        }
+        self.bytes.get(offset).copied().unwrap_or(self.text.len())
+    }
+    /// The text `span` covers.
+    pub fn get(&self, span: Span) -> &'a str {
+        &self.text[self.byte(span.start)..self.byte(span.end)]
+    }
+}